@@ -0,0 +1,123 @@
+//! Restricting access to files through their access control list, which is how Windows keeps other
+//! users from reading files such as the credentials file.
+
+use crate::error::*;
+use std::{ffi::c_void, path::Path, ptr};
+use windows::{
+    core::{HSTRING, PWSTR},
+    Win32::{
+        Foundation::{CloseHandle, LocalFree, HANDLE, HLOCAL, PSID},
+        Security::{
+            Authorization::{
+                ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW,
+                GetNamedSecurityInfoW, SDDL_REVISION_1, SE_FILE_OBJECT,
+            },
+            EqualSid, GetAce, GetTokenInformation, IsWellKnownSid, SetFileSecurityW, TokenUser,
+            WinBuiltinAdministratorsSid, WinLocalSystemSid, ACCESS_ALLOWED_ACE, ACE_HEADER, ACL,
+            DACL_SECURITY_INFORMATION, PROTECTED_DACL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR,
+            TOKEN_QUERY, TOKEN_USER,
+        },
+        System::{
+            SystemServices::{ACCESS_ALLOWED_ACE_TYPE, ACCESS_DENIED_ACE_TYPE},
+            Threading::{GetCurrentProcess, OpenProcessToken},
+        },
+    },
+};
+
+/// Replace the access control list of `path` with one which only gives the current user access,
+/// ignoring the permissions it would otherwise inherit from its directory.
+pub(crate) fn restrict_to_current_user(path: &Path) -> Result<()> {
+    with_current_user(|user| unsafe {
+        let mut sid = PWSTR::null();
+        ConvertSidToStringSidW(user, &mut sid)?;
+        let text = sid.to_string();
+        let _ = LocalFree(HLOCAL(sid.0.cast()));
+
+        // A protected list (`P`), which allows (`A`) the user full access (`FA`).
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            &HSTRING::from(format!("D:P(A;;FA;;;{})", text?)),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            None,
+        )?;
+        let result = SetFileSecurityW(
+            &HSTRING::from(path),
+            DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+            descriptor,
+        );
+        let _ = LocalFree(HLOCAL(descriptor.0));
+        result.ok()?;
+
+        Ok(())
+    })
+}
+
+/// Check whether only the current user, the system and administrators have access to `path`.
+pub(crate) fn is_private(path: &Path) -> Result<bool> {
+    with_current_user(|user| unsafe {
+        let mut acl: *mut ACL = ptr::null_mut();
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        GetNamedSecurityInfoW(
+            &HSTRING::from(path),
+            SE_FILE_OBJECT,
+            DACL_SECURITY_INFORMATION,
+            None,
+            None,
+            Some(&mut acl),
+            None,
+            &mut descriptor,
+        )?;
+
+        // Without an access control list at all, everyone has access.
+        let private = !acl.is_null()
+            && (0..u32::from((*acl).AceCount)).all(|index| {
+                let mut ace: *mut c_void = ptr::null_mut();
+                if GetAce(acl, index, &mut ace).is_err() {
+                    return false;
+                }
+
+                match u32::from((*(ace as *const ACE_HEADER)).AceType) {
+                    ACCESS_DENIED_ACE_TYPE => true,
+                    ACCESS_ALLOWED_ACE_TYPE => {
+                        let ace = ace as *mut ACCESS_ALLOWED_ACE;
+                        let sid = PSID(ptr::addr_of_mut!((*ace).SidStart).cast());
+                        EqualSid(sid, user).is_ok()
+                            || IsWellKnownSid(sid, WinLocalSystemSid).as_bool()
+                            || IsWellKnownSid(sid, WinBuiltinAdministratorsSid).as_bool()
+                    }
+                    // Entries which only give access under conditions are not worth the risk.
+                    _ => false,
+                }
+            });
+        let _ = LocalFree(HLOCAL(descriptor.0));
+
+        Ok(private)
+    })
+}
+
+/// Call `f` with the security identifier of the user `gobbler` runs as.
+fn with_current_user<R>(f: impl FnOnce(PSID) -> Result<R>) -> Result<R> {
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token)?;
+
+        // The first call fails, but tells how much room the information needs.
+        let mut length = 0;
+        let _ = GetTokenInformation(token, TokenUser, None, 0, &mut length);
+        // The information contains pointers, so the buffer is aligned to match.
+        let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+        let result = GetTokenInformation(
+            token,
+            TokenUser,
+            Some(buffer.as_mut_ptr().cast()),
+            length,
+            &mut length,
+        );
+        let _ = CloseHandle(token);
+        result?;
+
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        f(user.User.Sid)
+    }
+}
@@ -0,0 +1,444 @@
+//! Archiving every fetched feed item in a local SQLite database, so that posts can be searched for
+//! after they have dropped out of their feed, together with the history of fetching the feeds.
+
+use crate::{
+    error::*,
+    feed::{print_post, Badges, DisplayOptions, Feed},
+};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{io::Write, path::Path, time::Duration};
+use termcolor::StandardStream;
+use url::Url;
+
+/// The file used to store the archive, which is stored next to the subscriptions file.
+pub const ARCHIVE_FILE: &str = "archive.sqlite";
+
+/// The statements which bring the schema of the archive up to date, where the statements at index
+/// `n` upgrade version `n` of the schema to version `n + 1`. The version of the schema is stored
+/// as the `user_version` of the database.
+const MIGRATIONS: &[&str] = &[ITEMS_SCHEMA, FETCHES_SCHEMA, PUBLISHED_SCHEMA];
+
+/// Creates the tables which store the items. The full text index only refers to the items table,
+/// and is kept up to date by triggers.
+const ITEMS_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS items (
+        id INTEGER PRIMARY KEY,
+        feed TEXT NOT NULL,
+        item_id TEXT NOT NULL,
+        title TEXT NOT NULL,
+        url TEXT NOT NULL,
+        summary TEXT,
+        content TEXT,
+        author TEXT,
+        timestamp TEXT NOT NULL,
+        UNIQUE (feed, item_id)
+    );
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS items_fts USING fts5(
+        title, summary, content, author, content = 'items', content_rowid = 'id'
+    );
+
+    CREATE TRIGGER IF NOT EXISTS items_insert AFTER INSERT ON items BEGIN
+        INSERT INTO items_fts (rowid, title, summary, content, author)
+        VALUES (new.id, new.title, new.summary, new.content, new.author);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS items_delete AFTER DELETE ON items BEGIN
+        INSERT INTO items_fts (items_fts, rowid, title, summary, content, author)
+        VALUES ('delete', old.id, old.title, old.summary, old.content, old.author);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS items_update AFTER UPDATE ON items BEGIN
+        INSERT INTO items_fts (items_fts, rowid, title, summary, content, author)
+        VALUES ('delete', old.id, old.title, old.summary, old.content, old.author);
+        INSERT INTO items_fts (rowid, title, summary, content, author)
+        VALUES (new.id, new.title, new.summary, new.content, new.author);
+    END;
+";
+
+/// Creates the table which stores every attempt to fetch a feed.
+const FETCHES_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS fetches (
+        id INTEGER PRIMARY KEY,
+        feed TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        success INTEGER NOT NULL,
+        latency_ms INTEGER NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS fetches_feed ON fetches (feed);
+";
+
+/// Adds the time items were first published, which is the earliest time known for an item,
+/// either from its feed or from when it was first archived.
+const PUBLISHED_SCHEMA: &str = "
+    ALTER TABLE items ADD COLUMN published TEXT;
+    UPDATE items SET published = timestamp;
+";
+
+/// The number of words around a match shown in the excerpt of a search result.
+const SNIPPET_WORDS: i64 = 16;
+
+/// A post found in the archive.
+pub struct ArchivedItem {
+    /// The name of the feed the post belongs to
+    pub feed: String,
+
+    /// The id of the post
+    pub id: String,
+
+    /// The title of the post
+    pub title: String,
+
+    /// The url of the post
+    pub url: String,
+
+    /// The timestamp of the post
+    pub timestamp: DateTime<FixedOffset>,
+
+    /// An excerpt of the post around the words which were searched for
+    pub snippet: Option<String>,
+}
+
+impl ArchivedItem {
+    /// Writes the post to the given [`StandardStream`].
+    pub fn print_colored(
+        &self,
+        stdout: &mut StandardStream,
+        options: &DisplayOptions,
+    ) -> Result<()> {
+        let timestamp = format!("{} · {}", options.dates.format(self.timestamp), self.feed);
+        print_post(
+            stdout,
+            "    ",
+            &timestamp,
+            &self.title,
+            &Url::parse(&self.url)?,
+            Badges::default(),
+            options,
+        )?;
+
+        if let Some(snippet) = self
+            .snippet
+            .as_deref()
+            .filter(|snippet| !snippet.is_empty())
+        {
+            writeln!(
+                stdout,
+                "        {}",
+                snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How often fetching a feed succeeded, and how long it took.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FetchHistory {
+    /// The number of times the feed was fetched
+    pub fetches: usize,
+
+    /// The number of times fetching the feed succeeded
+    pub successes: usize,
+
+    /// The total time the successful fetches took
+    pub total_latency: Duration,
+}
+
+impl FetchHistory {
+    /// Get the fraction of the fetches which succeeded, if the feed was fetched at all.
+    pub fn success_rate(&self) -> Option<f64> {
+        (self.fetches > 0).then(|| self.successes as f64 / self.fetches as f64)
+    }
+
+    /// Get the average time a successful fetch took, if any fetch succeeded.
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.successes > 0).then(|| self.total_latency / self.successes as u32)
+    }
+}
+
+impl std::ops::Add for FetchHistory {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            fetches: self.fetches + other.fetches,
+            successes: self.successes + other.successes,
+            total_latency: self.total_latency + other.total_latency,
+        }
+    }
+}
+
+/// The archive of every item which has been fetched.
+pub struct Archive {
+    connection: Connection,
+}
+
+impl Archive {
+    /// Open the archive next to `subscriptions_file`, creating it if it does not exist yet.
+    pub fn next_to(subscriptions_file: &Path) -> Result<Self> {
+        Self::open(&subscriptions_file.with_file_name(ARCHIVE_FILE))
+    }
+
+    /// Open the archive at `path`, creating it if it does not exist yet.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut connection = Connection::open(path)?;
+
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (version, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", version + 1)?;
+            transaction.commit()?;
+        }
+
+        Ok(Self { connection })
+    }
+
+    /// Store the items of `feed`, updating the items which were already stored. Items are
+    /// remembered by their id, so that the time an item was first published is known even if its
+    /// feed doesn't say. The `published` time of every item of `feed` is set to the earliest time
+    /// known for it.
+    pub fn store(&mut self, feed: &mut Feed) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO items
+                     (feed, item_id, title, url, summary, content, author, timestamp, published)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT (feed, item_id) DO UPDATE SET
+                     title = excluded.title,
+                     url = excluded.url,
+                     summary = excluded.summary,
+                     content = excluded.content,
+                     author = excluded.author,
+                     timestamp = excluded.timestamp,
+                     published = MIN(COALESCE(published, excluded.published), excluded.published)
+                 WHERE title IS NOT excluded.title
+                     OR summary IS NOT excluded.summary
+                     OR content IS NOT excluded.content
+                     OR author IS NOT excluded.author
+                     OR timestamp IS NOT excluded.timestamp
+                     OR published IS NULL
+                     OR published > excluded.published",
+            )?;
+            let mut published = transaction
+                .prepare_cached("SELECT published FROM items WHERE feed = ?1 AND item_id = ?2")?;
+            let name = feed.name().to_string();
+            for index in 0..feed.items.len() {
+                let item = &feed.items[index];
+                let first_seen = item.published.unwrap_or(item.timestamp);
+                insert.execute(params![
+                    name,
+                    item.id,
+                    item.title,
+                    feed.post_url(item)?.as_str(),
+                    item.summary,
+                    item.content,
+                    item.author,
+                    format_timestamp(item.timestamp.with_timezone(&Utc)),
+                    format_timestamp(first_seen.with_timezone(&Utc)),
+                ])?;
+
+                let archived: String =
+                    published.query_row(params![name, item.id], |row| row.get(0))?;
+                let archived = DateTime::parse_from_rfc3339(&archived)?;
+                if archived < first_seen {
+                    feed.items[index].published = Some(archived);
+                }
+            }
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    /// Search for the posts matching `query`, which is a list of words which all have to occur in
+    /// the title, summary, content or author of a post. Words ending in `*` match any word
+    /// starting with them. Only posts of the feed named `feed` and posts after `since` are
+    /// returned, if given. Returns at most `limit` posts, the best matches first.
+    pub fn search(
+        &self,
+        query: &str,
+        feed: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> Result<Vec<ArchivedItem>> {
+        let query = to_fts_query(query);
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT items.feed, items.item_id, items.title, items.url, items.timestamp,
+                    snippet(items_fts, -1, '', '', '…', ?4)
+             FROM items_fts JOIN items ON items.id = items_fts.rowid
+             WHERE items_fts MATCH ?1
+                 AND (?2 IS NULL OR items.feed = ?2)
+                 AND (?3 IS NULL OR items.timestamp >= ?3)
+             ORDER BY rank
+             LIMIT ?5",
+        )?;
+        let rows = statement.query_map(
+            params![
+                query,
+                feed,
+                since.map(format_timestamp),
+                SNIPPET_WORDS,
+                limit as i64
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            },
+        )?;
+
+        let mut items = vec![];
+        for row in rows {
+            let (feed, id, title, url, timestamp, snippet) = row?;
+            items.push(ArchivedItem {
+                feed,
+                id,
+                title,
+                url,
+                timestamp: DateTime::parse_from_rfc3339(&timestamp)?,
+                snippet,
+            });
+        }
+
+        Ok(items)
+    }
+
+    /// Record an attempt to fetch the feed named `feed` at `timestamp`, which took `latency`.
+    pub fn record_fetch(
+        &mut self,
+        feed: &str,
+        timestamp: DateTime<Utc>,
+        success: bool,
+        latency: Duration,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO fetches (feed, timestamp, success, latency_ms) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                feed,
+                format_timestamp(timestamp),
+                success,
+                latency.as_millis() as i64
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Get the times the archived posts of the feed named `feed` were first published, oldest
+    /// first. Posts archived before that time was known fall back to when they were last updated.
+    pub fn post_timestamps(&self, feed: &str) -> Result<Vec<DateTime<FixedOffset>>> {
+        let mut statement = self.connection.prepare(
+            "SELECT COALESCE(published, timestamp) AS first_seen FROM items WHERE feed = ?1
+                 ORDER BY first_seen",
+        )?;
+        let rows = statement.query_map(params![feed], |row| row.get::<_, String>(0))?;
+
+        let mut timestamps = vec![];
+        for row in rows {
+            timestamps.push(DateTime::parse_from_rfc3339(&row?)?);
+        }
+
+        Ok(timestamps)
+    }
+
+    /// Get the history of fetching the feed named `feed`.
+    pub fn fetch_history(&self, feed: &str) -> Result<FetchHistory> {
+        let (fetches, successes, latency_ms) = self.connection.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(success), 0),
+                    COALESCE(SUM(CASE WHEN success THEN latency_ms ELSE 0 END), 0)
+             FROM fetches WHERE feed = ?1",
+            params![feed],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+
+        Ok(FetchHistory {
+            fetches: fetches as usize,
+            successes: successes as usize,
+            total_latency: Duration::from_millis(latency_ms as u64),
+        })
+    }
+
+    /// Find the post whose id or url is `id`, only looking at the posts of the feed named `feed` if
+    /// given.
+    pub fn find(&self, feed: Option<&str>, id: &str) -> Result<Option<ArchivedItem>> {
+        let row = self
+            .connection
+            .query_row(
+                "SELECT feed, item_id, title, url, timestamp FROM items
+                 WHERE (?1 IS NULL OR feed = ?1) AND (item_id = ?2 OR url = ?2)
+                 ORDER BY timestamp DESC
+                 LIMIT 1",
+                params![feed, id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((feed, id, title, url, timestamp)) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(ArchivedItem {
+            feed,
+            id,
+            title,
+            url,
+            timestamp: DateTime::parse_from_rfc3339(&timestamp)?,
+            snippet: None,
+        }))
+    }
+}
+
+/// Format `timestamp` so that timestamps can be compared as strings.
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Convert `query`, a list of words, to a full text query which matches posts containing all
+/// words. Every word is quoted, so that characters which have a special meaning in full text
+/// queries are matched literally.
+fn to_fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .filter_map(|word| {
+            let (word, prefix) = match word.strip_suffix('*') {
+                Some(word) => (word, "*"),
+                None => (word, ""),
+            };
+            if word.is_empty() {
+                return None;
+            }
+
+            Some(format!("\"{}\"{}", word.replace('"', "\"\""), prefix))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
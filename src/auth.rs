@@ -0,0 +1,424 @@
+//! Authenticating to feeds which require credentials, using HTTP basic authentication, a bearer
+//! token or custom headers. Subscriptions only store references to secrets, which are resolved
+//! every time a feed is fetched, so that secrets never end up in the subscriptions file.
+
+#[cfg(not(unix))]
+use crate::acl;
+use crate::{
+    error::*,
+    hook::shell,
+    lines::{read_lines, write_lines},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    env,
+    fmt::{self, Debug},
+    fs::OpenOptions,
+    path::Path,
+    str::FromStr,
+};
+use url::Url;
+
+/// The file used to store secrets, which is stored next to the subscriptions file.
+pub const CREDENTIALS_FILE: &str = "credentials.db";
+
+/// The text secrets are replaced with in output and errors.
+const REDACTED: &str = "***";
+
+/// A reference to a secret, such as a password or a token. Only the reference is stored, the
+/// secret itself is looked up when it is needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Secret {
+    /// The value of an environment variable, written as `env:VARIABLE`
+    Env(String),
+    /// A secret stored under a key in the credentials file, written as `file:KEY`
+    File(String),
+    /// The first line printed by a command, such as `cmd:pass show gitlab`
+    Command(String),
+}
+
+impl Secret {
+    /// Look up the secret, reading secrets stored in the credentials file from
+    /// `credentials_file`.
+    pub fn resolve(&self, credentials_file: &Path) -> Result<String> {
+        let secret = match self {
+            Secret::Env(variable) => env::var(variable).map_err(|error| self.failed(error))?,
+            Secret::File(key) => read_credentials(credentials_file)?
+                .into_iter()
+                .find(|(stored, _)| stored == key)
+                .map(|(_, secret)| secret)
+                .ok_or_else(|| {
+                    self.failed(format!("not found in '{}'", credentials_file.display()))
+                })?,
+            Secret::Command(command) => {
+                let output = shell(command)
+                    .output()
+                    .map_err(|error| self.failed(error))?;
+                if !output.status.success() {
+                    return Err(self.failed(format!("the command failed ({})", output.status)));
+                }
+
+                // Password managers such as `pass` print the password on the first line, which
+                // may be followed by other fields.
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            }
+        };
+
+        if secret.is_empty() {
+            return Err(self.failed("the secret is empty"));
+        }
+        if secret.contains(['\r', '\n']) {
+            return Err(self.failed("the secret contains line breaks"));
+        }
+
+        Ok(secret)
+    }
+
+    /// Create the error returned when the secret cannot be resolved.
+    fn failed(&self, reason: impl fmt::Display) -> Error {
+        Error::ResolveSecret {
+            secret: self.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Error;
+
+    fn from_str(reference: &str) -> Result<Self> {
+        // Every subscription is stored on a single line, with its attributes separated by tabs.
+        if reference.contains(['\t', '\r', '\n']) {
+            return Err(Error::InvalidSecret);
+        }
+
+        let secret = match reference.split_once(':') {
+            Some(("env", variable)) => Secret::Env(variable.to_string()),
+            Some(("file", key)) => Secret::File(key.to_string()),
+            Some(("cmd", command)) => Secret::Command(command.to_string()),
+            _ => return Err(Error::InvalidSecret),
+        };
+
+        match &secret {
+            Secret::Env(value) | Secret::File(value) | Secret::Command(value)
+                if value.trim().is_empty() =>
+            {
+                Err(Error::InvalidSecret)
+            }
+            _ => Ok(secret),
+        }
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Env(variable) => write!(f, "env:{}", variable),
+            Secret::File(key) => write!(f, "file:{}", key),
+            Secret::Command(command) => write!(f, "cmd:{}", command),
+        }
+    }
+}
+
+/// The credentials a feed is fetched with. Only references to the secrets are stored, see
+/// [`Secret`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Auth {
+    /// The user name and password used for HTTP basic authentication
+    pub basic: Option<(String, Secret)>,
+
+    /// The token sent in a bearer `Authorization` header
+    pub bearer: Option<Secret>,
+
+    /// Custom headers, such as `X-Api-Key`, by name
+    pub headers: Vec<(String, Secret)>,
+}
+
+impl Auth {
+    /// Create a new [`Auth`] from the values of the `--basic` (`USER:SECRET`), `--bearer`
+    /// (`SECRET`) and `--header` (`NAME: SECRET`) options.
+    pub fn parse(basic: Option<&str>, bearer: Option<&str>, headers: &[String]) -> Result<Self> {
+        let basic = match basic {
+            Some(basic) => {
+                let (user, password) = basic.split_once(':').ok_or(Error::InvalidBasicAuth)?;
+                if user.is_empty() || user.contains(['\t', '\r', '\n']) {
+                    return Err(Error::InvalidBasicAuth);
+                }
+                Some((user.to_string(), password.parse()?))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            basic,
+            bearer: bearer.map(str::parse).transpose()?,
+            headers: headers
+                .iter()
+                .map(|header| parse_header(header))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Check whether no credentials are sent at all.
+    pub fn is_empty(&self) -> bool {
+        self.basic.is_none() && self.bearer.is_none() && self.headers.is_empty()
+    }
+
+    /// Check whether any of the secrets is printed by a command, see [`Secret::Command`].
+    pub fn runs_commands(&self) -> bool {
+        let is_command = |secret: &Secret| matches!(secret, Secret::Command(_));
+
+        self.basic.iter().any(|(_, password)| is_command(password))
+            || self.bearer.iter().any(is_command)
+            || self.headers.iter().any(|(_, secret)| is_command(secret))
+    }
+
+    /// Look up all secrets, reading secrets stored in the credentials file from
+    /// `credentials_file`, so that the credentials can be sent along with requests.
+    pub fn resolve(&self, credentials_file: &Path) -> Result<Credentials> {
+        let mut credentials = Credentials::default();
+        if let Some((user, password)) = &self.basic {
+            let password = password.resolve(credentials_file)?;
+            let encoded = STANDARD.encode(format!("{}:{}", user, password));
+            credentials.add("Authorization", format!("Basic {}", encoded), &password);
+            credentials.secrets.push(encoded);
+        }
+        if let Some(bearer) = &self.bearer {
+            let token = bearer.resolve(credentials_file)?;
+            credentials.add("Authorization", format!("Bearer {}", token), &token);
+        }
+        for (name, secret) in &self.headers {
+            let value = secret.resolve(credentials_file)?;
+            credentials.add(name, value.clone(), &value);
+        }
+
+        Ok(credentials)
+    }
+
+    /// Describe how the feed is authenticated, without revealing any secrets, such as
+    /// `basic (alice), header X-Api-Key`.
+    pub fn describe(&self) -> String {
+        let mut kinds = vec![];
+        if let Some((user, _)) = &self.basic {
+            kinds.push(format!("basic ({})", user));
+        }
+        if self.bearer.is_some() {
+            kinds.push(String::from("bearer"));
+        }
+        for (name, _) in &self.headers {
+            kinds.push(format!("header {}", name));
+        }
+
+        kinds.join(", ")
+    }
+
+    /// Parse an attribute of a line of the subscriptions file, ignoring attributes which are not
+    /// used for authentication.
+    pub(crate) fn parse_attribute(&mut self, key: &str, value: &str) {
+        match key {
+            "basic" => {
+                self.basic = value
+                    .split_once(':')
+                    .and_then(|(user, password)| Some((user.to_string(), password.parse().ok()?)))
+            }
+            "bearer" => self.bearer = value.parse().ok(),
+            "header" => self.headers.extend(parse_header(value).ok()),
+            _ => {}
+        }
+    }
+
+    /// Format the credentials as attributes of a line of the subscriptions file.
+    pub(crate) fn to_attributes(&self) -> Vec<String> {
+        let mut attributes = vec![];
+        if let Some((user, password)) = &self.basic {
+            attributes.push(format!("basic={}:{}", user, password));
+        }
+        if let Some(bearer) = &self.bearer {
+            attributes.push(format!("bearer={}", bearer));
+        }
+        for (name, secret) in &self.headers {
+            attributes.push(format!("header={}: {}", name, secret));
+        }
+
+        attributes
+    }
+}
+
+/// Parse a header of the form `NAME: SECRET`.
+fn parse_header(header: &str) -> Result<(String, Secret)> {
+    let (name, secret) = header.split_once(':').ok_or(Error::InvalidHeader)?;
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+    {
+        return Err(Error::InvalidHeader);
+    }
+
+    Ok((name.to_string(), secret.trim().parse()?))
+}
+
+/// Credentials with all their secrets looked up, which are sent along with requests. The secrets
+/// are never shown, not even when debugging.
+#[derive(Default)]
+pub struct Credentials {
+    /// The headers which are sent with every request
+    headers: Vec<(String, String)>,
+
+    /// The secrets, which are redacted from errors
+    secrets: Vec<String>,
+}
+
+impl Credentials {
+    /// Add a header containing `secret`.
+    fn add(&mut self, name: &str, value: String, secret: &str) {
+        self.headers.push((name.to_string(), value));
+        self.secrets.push(secret.to_string());
+    }
+
+    /// Get the headers which carry the credentials.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Replace every secret in `text` with `***`.
+    pub fn redact(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        })
+    }
+
+    /// Replace `error` with a redacted copy of its message if the message contains a secret.
+    pub fn redact_error(&self, error: Error) -> Error {
+        let message = error.to_string();
+        if self.secrets.iter().any(|secret| message.contains(secret)) {
+            Error::Redacted(self.redact(&message))
+        } else {
+            error
+        }
+    }
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.headers.iter().map(|(name, _)| name).collect();
+        f.debug_struct("Credentials")
+            .field("headers", &names)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Check that `url` does not contain a password, which would be stored in the subscriptions file.
+pub fn check_url(url: &str) -> Result<()> {
+    match Url::parse(url) {
+        Ok(url) if url.password().is_some() => Err(Error::PasswordInUrl),
+        _ => Ok(()),
+    }
+}
+
+/// Store `secret` under `key` in `credentials_file`, replacing the secret stored under that key,
+/// or remove the secret stored under `key` if `secret` is `None`. Returns `false` if there was no
+/// secret to remove.
+pub fn store_credential(credentials_file: &Path, key: &str, secret: Option<&str>) -> Result<bool> {
+    if key.is_empty() || key.contains(['\t', '\r', '\n']) {
+        return Err(Error::InvalidCredentialKey(key.to_string()));
+    }
+    if secret.is_some_and(|secret| secret.is_empty() || secret.contains(['\r', '\n'])) {
+        return Err(Error::InvalidSecret);
+    }
+
+    let mut credentials = read_credentials(credentials_file)?;
+    let existed = credentials.iter().any(|(stored, _)| stored == key);
+    credentials.retain(|(stored, _)| stored != key);
+    match secret {
+        Some(secret) => credentials.push((key.to_string(), secret.to_string())),
+        None if !existed => return Ok(false),
+        None => {}
+    }
+    credentials.sort();
+
+    create_private_file(credentials_file)?;
+    write_lines(
+        credentials_file,
+        credentials
+            .into_iter()
+            .map(|(key, secret)| format!("{}\t{}", key, secret)),
+    )?;
+
+    Ok(true)
+}
+
+/// Get the keys of the secrets stored in `credentials_file`.
+pub fn credential_keys(credentials_file: &Path) -> Result<Vec<String>> {
+    Ok(read_credentials(credentials_file)?
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect())
+}
+
+/// Read the secrets stored in `credentials_file`, by key. Lines have the form `key<TAB>secret`.
+fn read_credentials(credentials_file: &Path) -> Result<Vec<(String, String)>> {
+    check_permissions(credentials_file)?;
+
+    Ok(read_lines(credentials_file)?
+        .into_iter()
+        .filter_map(|line| {
+            let (key, secret) = line.split_once('\t')?;
+            Some((key.to_string(), secret.to_string()))
+        })
+        .collect())
+}
+
+/// Create `path` if it does not exist yet, so that only the current user can read it.
+#[cfg(unix)]
+fn create_private_file(path: &Path) -> Result<()> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(path)?;
+
+    Ok(())
+}
+
+/// Create `path` if it does not exist yet, so that only the current user can access it.
+#[cfg(not(unix))]
+fn create_private_file(path: &Path) -> Result<()> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => acl::restrict_to_current_user(path),
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Refuse to use `path` if other users can read or write it.
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o077 != 0 => {
+            Err(Error::InsecureCredentialsFile(path.to_path_buf()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuse to use `path` if users other than the current user, the system and administrators can
+/// access it.
+#[cfg(not(unix))]
+fn check_permissions(path: &Path) -> Result<()> {
+    if path.exists() && !acl::is_private(path)? {
+        return Err(Error::InsecureCredentialsFile(path.to_path_buf()));
+    }
+
+    Ok(())
+}
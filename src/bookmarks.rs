@@ -0,0 +1,139 @@
+//! Exporting starred items, so that they can be shared or imported into a web browser.
+
+use crate::{error::*, marks::StarredItem, xml::escape_xml};
+use chrono::SecondsFormat;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// The formats in which starred items can be exported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// A Markdown list of links for every feed
+    Markdown,
+    /// A JSON array
+    Json,
+    /// Netscape bookmarks HTML, which can be imported by web browsers, with a folder for every
+    /// feed
+    Html,
+}
+
+/// A [`StarredItem`] in the form in which it is exported as JSON.
+#[derive(Serialize)]
+struct ExportedBookmark<'a> {
+    /// The name of the feed the item belongs to
+    feed: &'a str,
+    /// The title of the item
+    title: &'a str,
+    /// The url of the item
+    url: &'a str,
+    /// The id of the item
+    id: &'a str,
+    /// The timestamp of the item, in RFC 3339 format
+    timestamp: String,
+}
+
+/// Format `items` in the given format.
+pub fn export<'a>(
+    format: ExportFormat,
+    items: impl IntoIterator<Item = &'a StarredItem>,
+) -> Result<String> {
+    let items: Vec<_> = items.into_iter().collect();
+    match format {
+        ExportFormat::Markdown => Ok(to_markdown(&items)),
+        ExportFormat::Json => to_json(&items),
+        ExportFormat::Html => Ok(to_html(&items)),
+    }
+}
+
+/// Format `items` as Markdown, with a heading for every feed followed by a list of links to its
+/// items, newest first.
+pub fn to_markdown(items: &[&StarredItem]) -> String {
+    let mut markdown = String::from("# Starred posts\n");
+    for (feed, items) in by_feed(items) {
+        markdown.push_str(&format!("\n## {}\n\n", escape_markdown(feed)));
+        for item in items {
+            markdown.push_str(&format!(
+                "- [{}]({}) ({})\n",
+                escape_markdown(&item.title),
+                item.url.replace('(', "%28").replace(')', "%29"),
+                item.timestamp.format("%Y-%m-%d")
+            ));
+        }
+    }
+
+    markdown
+}
+
+/// Format `items` as a JSON array, newest first.
+pub fn to_json(items: &[&StarredItem]) -> Result<String> {
+    let mut items = items.to_vec();
+    items.sort_by_key(|item| Reverse(item.timestamp));
+
+    let exported: Vec<_> = items
+        .into_iter()
+        .map(|item| ExportedBookmark {
+            feed: &item.feed,
+            title: &item.title,
+            url: &item.url,
+            id: &item.id,
+            timestamp: item.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        })
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&exported)?)
+}
+
+/// Format `items` as a Netscape bookmarks file, which is the format web browsers import and export
+/// bookmarks in, with a folder for every feed.
+pub fn to_html(items: &[&StarredItem]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    for (feed, items) in by_feed(items) {
+        html.push_str(&format!("    <DT><H3>{}</H3>\n", escape_xml(feed)));
+        html.push_str("    <DL><p>\n");
+        for item in items {
+            html.push_str(&format!(
+                "        <DT><A HREF=\"{}\" ADD_DATE=\"{}\">{}</A>\n",
+                escape_xml(&item.url),
+                item.timestamp.timestamp(),
+                escape_xml(&item.title)
+            ));
+        }
+        html.push_str("    </DL><p>\n");
+    }
+    html.push_str("</DL><p>\n");
+
+    html
+}
+
+/// Group `items` by the name of their feed, newest first.
+fn by_feed<'a>(items: &[&'a StarredItem]) -> BTreeMap<&'a str, Vec<&'a StarredItem>> {
+    let mut feeds: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for item in items {
+        feeds.entry(item.feed.as_str()).or_default().push(*item);
+    }
+    for items in feeds.values_mut() {
+        items.sort_by_key(|item| Reverse(item.timestamp));
+    }
+
+    feeds
+}
+
+/// Escape the characters of `text` which would otherwise be taken as Markdown.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`' | '<' | '>' | '#') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
@@ -0,0 +1,156 @@
+//! Opening posts in the web browser, or in any other program which is configured to open them.
+
+use crate::{error::*, http::is_web_url};
+use std::process::{Command, Stdio};
+
+/// The placeholder in an opener command which is replaced by the url to open.
+const URL_PLACEHOLDER: &str = "{}";
+
+/// Opens urls in the web browser, or using a configured command.
+pub struct Opener {
+    /// The command which opens urls, if configured
+    command: Option<String>,
+}
+
+impl Opener {
+    /// Create a new [`Opener`] which opens urls using `command`, or in the default web browser if
+    /// no command is given. The command is split on whitespace into a program and its arguments,
+    /// unless the whitespace is within double quotes, such as in `"C:\Program Files\app.exe" {}`.
+    /// In the arguments, `{}` is replaced by the url. If `{}` does not occur, the url is passed as
+    /// the last argument.
+    pub fn new(command: Option<String>) -> Self {
+        Self {
+            command: command.filter(|command| !command.trim().is_empty()),
+        }
+    }
+
+    /// Open `url`, without waiting for the program which opens it to close. Only `http` and `https`
+    /// urls are opened, as opening urls such as `file:` urls could start a program.
+    pub fn open(&self, url: &str) -> Result<()> {
+        if !is_web_url(url) {
+            return Err(Error::UnsafeUrl(url.to_string()));
+        }
+
+        let mut command = self.command_for(url);
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|source| Error::OpenBrowser {
+                command: describe(&command),
+                source,
+            })?;
+
+        Ok(())
+    }
+
+    /// Describe the command which would be run to open `url`.
+    pub fn describe(&self, url: &str) -> String {
+        describe(&self.command_for(url))
+    }
+
+    /// Create the [`Command`] which opens `url`.
+    fn command_for(&self, url: &str) -> Command {
+        let Some(configured) = &self.command else {
+            return browser_command(url);
+        };
+
+        // The url is passed as an argument rather than through the shell, so it can't be used to run
+        // other commands.
+        let mut parts = split_command(configured).into_iter();
+        let mut command = Command::new(parts.next().unwrap_or_default());
+        let mut has_placeholder = false;
+        for part in parts {
+            if part.contains(URL_PLACEHOLDER) {
+                has_placeholder = true;
+                command.arg(part.replace(URL_PLACEHOLDER, url));
+            } else {
+                command.arg(part);
+            }
+        }
+        if !has_placeholder {
+            command.arg(url);
+        }
+
+        command
+    }
+}
+
+/// Split `command` into a program and its arguments on whitespace, except for whitespace within
+/// double quotes. The quotes themselves are removed.
+fn split_command(command: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in command.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    parts.push(std::mem::take(&mut part));
+                    started = false;
+                }
+            }
+            c => {
+                part.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        parts.push(part);
+    }
+
+    parts
+}
+
+/// Describe `command` the way it would be typed in a shell.
+fn describe(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| {
+            let part = part.to_string_lossy();
+            if part.contains(char::is_whitespace) || part.is_empty() {
+                format!("\"{}\"", part)
+            } else {
+                part.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Create the [`Command`] which opens `url` in the default web browser.
+#[cfg(windows)]
+fn browser_command(url: &str) -> Command {
+    // Unlike `start`, this doesn't require quoting the url for `cmd`.
+    let mut command = Command::new("rundll32");
+    command.arg("url.dll,FileProtocolHandler").arg(url);
+    command
+}
+
+/// Create the [`Command`] which opens `url` in the default web browser, which is `$BROWSER` if
+/// it is set.
+#[cfg(not(windows))]
+fn browser_command(url: &str) -> Command {
+    let browser = std::env::var("BROWSER")
+        .ok()
+        .and_then(|browsers| browsers.split(':').next().map(str::to_string))
+        .filter(|browser| !browser.is_empty());
+    let browser = browser.unwrap_or_else(|| {
+        String::from(if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        })
+    });
+
+    let mut command = Command::new(browser);
+    command.arg(url);
+    command
+}
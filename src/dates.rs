@@ -0,0 +1,146 @@
+//! Formatting the timestamps of posts in a single time zone, so that posts from different feeds
+//! can be compared.
+
+use crate::error::*;
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Duration, FixedOffset, Local, NaiveDate, Utc,
+};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+/// The format used for timestamps unless another format is given.
+pub const DEFAULT_DATE_FORMAT: &str = "%c";
+
+/// The format used for timestamps when posts are grouped by day, unless another format is given.
+pub const DEFAULT_GROUPED_DATE_FORMAT: &str = "%H:%M";
+
+/// The format used for days in headings, other than today and yesterday.
+const DAY_HEADING_FORMAT: &str = "%A, %B %-d %Y";
+
+/// The format used for timestamps which are too long ago to show relative to now.
+const RELATIVE_FALLBACK_FORMAT: &str = "%Y-%m-%d";
+
+/// The time zone in which timestamps are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Zone {
+    /// The local time zone
+    Local,
+    /// A time zone from the IANA time zone database, such as `Europe/Amsterdam`
+    Named(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = Error;
+
+    fn from_str(zone: &str) -> Result<Self> {
+        if zone.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+
+        zone.parse::<Tz>()
+            .map(Zone::Named)
+            .map_err(|_| Error::UnknownTimeZone(zone.to_string()))
+    }
+}
+
+/// Formats the timestamps of posts in a single time zone, either using a format string or
+/// relative to now.
+pub struct DateFormatter {
+    /// The time zone in which timestamps are shown
+    zone: Zone,
+
+    /// The `strftime` style format of timestamps, or `None` to show them relative to now
+    format: Option<String>,
+}
+
+impl DateFormatter {
+    /// Create a new [`DateFormatter`] which shows timestamps in `zone`, using `format`, which is
+    /// a `strftime` style format such as `%Y-%m-%d %H:%M`.
+    pub fn new(zone: Zone, format: &str) -> Result<Self> {
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(Error::InvalidDateFormat(format.to_string()));
+        }
+
+        Ok(Self {
+            zone,
+            format: Some(format.to_string()),
+        })
+    }
+
+    /// Create a new [`DateFormatter`] which shows timestamps relative to now, such as `3h ago`,
+    /// using `zone` to determine what yesterday is.
+    pub fn relative(zone: Zone) -> Self {
+        Self { zone, format: None }
+    }
+
+    /// Format `timestamp`.
+    pub fn format(&self, timestamp: DateTime<FixedOffset>) -> String {
+        match &self.format {
+            Some(format) => self.format_with(timestamp, format),
+            None => self.format_relative(timestamp, Utc::now()),
+        }
+    }
+
+    /// Get the day on which `timestamp` falls in the time zone of the formatter.
+    pub fn day_of(&self, timestamp: DateTime<FixedOffset>) -> NaiveDate {
+        match self.zone {
+            Zone::Local => timestamp.with_timezone(&Local).date_naive(),
+            Zone::Named(zone) => timestamp.with_timezone(&zone).date_naive(),
+        }
+    }
+
+    /// Format `day` for use in a heading, such as `Today` or `Monday, May 6 2024`.
+    pub fn format_day(&self, day: NaiveDate) -> String {
+        let today = self.day_of(Utc::now().fixed_offset());
+        if day == today {
+            String::from("Today")
+        } else if today.pred_opt() == Some(day) {
+            String::from("Yesterday")
+        } else {
+            day.format(DAY_HEADING_FORMAT).to_string()
+        }
+    }
+
+    /// Format `timestamp` in the time zone of the formatter using `format`.
+    fn format_with(&self, timestamp: DateTime<FixedOffset>, format: &str) -> String {
+        match self.zone {
+            Zone::Local => timestamp.with_timezone(&Local).format(format).to_string(),
+            Zone::Named(zone) => timestamp.with_timezone(&zone).format(format).to_string(),
+        }
+    }
+
+    /// Format `timestamp` relative to `now`. Timestamps in the future, or more than a few weeks
+    /// ago, are shown as a date instead.
+    fn format_relative(&self, timestamp: DateTime<FixedOffset>, now: DateTime<Utc>) -> String {
+        let age = now.signed_duration_since(timestamp);
+        let yesterday = self.day_of(now.fixed_offset()).pred_opt();
+
+        if age < Duration::zero() || age >= Duration::weeks(5) {
+            self.format_with(timestamp, RELATIVE_FALLBACK_FORMAT)
+        } else if age < Duration::minutes(1) {
+            String::from("just now")
+        } else if age < Duration::hours(1) {
+            format!("{}m ago", age.num_minutes())
+        } else if age < Duration::hours(24) {
+            format!("{}h ago", age.num_hours())
+        } else if Some(self.day_of(timestamp)) == yesterday {
+            String::from("yesterday")
+        } else if age < Duration::weeks(1) {
+            // Count calendar days, so that a post from two days ago is never shown as `1d ago`.
+            let days = (self.day_of(now.fixed_offset()) - self.day_of(timestamp)).num_days();
+            format!("{}d ago", days)
+        } else {
+            format!("{}w ago", age.num_weeks())
+        }
+    }
+}
+
+impl Default for DateFormatter {
+    fn default() -> Self {
+        Self {
+            zone: Zone::Local,
+            format: Some(String::from(DEFAULT_DATE_FORMAT)),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Detecting posts which appear in multiple feeds, such as a post on an author's blog which is
+//! also carried by a planet aggregator, so that it is only shown once.
+
+use crate::feed::Feed;
+use chrono::Duration;
+use std::collections::{BTreeSet, HashMap};
+use url::Url;
+
+/// Query parameters which are only used to track where visitors come from, and which are ignored
+/// when comparing urls. Parameters starting with `utm_` are ignored as well.
+const TRACKING_PARAMETERS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_hsenc",
+    "_hsmi", "ref", "ref_src", "source",
+];
+
+/// The fraction of the words two titles must have in common to be taken as the same post.
+const TITLE_SIMILARITY: f64 = 0.8;
+
+/// The fewest words a title must have to be compared with other titles, as short titles such as
+/// `Weekly update` are too likely to be shared by unrelated posts.
+const MIN_TITLE_WORDS: usize = 3;
+
+/// How many days apart posts with similar titles may be published to be taken as the same post.
+const MAX_TITLE_MATCH_DAYS: i64 = 7;
+
+/// Remove the posts from `feeds` which also appear in another feed, keeping only the earliest
+/// copy of every post. Posts are the same when their urls are the same after normalizing them,
+/// see [`normalize_url`], or, if `fuzzy_titles` is set, when their titles are similar. The names
+/// of the feeds from which a copy was removed are recorded in the feed of the copy which is kept,
+/// see [`Feed::also_in`].
+pub fn dedupe(feeds: &mut [Feed], fuzzy_titles: bool) {
+    // Visit the posts oldest first, so that the original post is kept rather than a copy.
+    let mut posts: Vec<_> = feeds
+        .iter()
+        .enumerate()
+        .flat_map(|(index, feed)| (0..feed.items.len()).map(move |item| (index, item)))
+        .collect();
+    posts.sort_by_key(|&(feed, item)| (feeds[feed].items[item].timestamp, feed));
+
+    // The posts which are kept, by normalized url, and with the words of their titles.
+    let mut by_url: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut titles: Vec<((usize, usize), BTreeSet<String>)> = vec![];
+    let mut duplicates: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+    for (feed, item) in posts {
+        let post = &feeds[feed].items[item];
+        let url = feeds[feed]
+            .post_url(post)
+            .map(|url| normalize_url(&url))
+            .unwrap_or_else(|_| post.id.clone());
+        let words = title_words(&post.title);
+
+        let original = by_url.get(&url).copied().or_else(|| {
+            if !fuzzy_titles || words.len() < MIN_TITLE_WORDS {
+                return None;
+            }
+
+            titles
+                .iter()
+                .find(|((other_feed, other_item), other_words)| {
+                    let other = &feeds[*other_feed].items[*other_item];
+                    post.timestamp.signed_duration_since(other.timestamp)
+                        <= Duration::days(MAX_TITLE_MATCH_DAYS)
+                        && similarity(&words, other_words) >= TITLE_SIMILARITY
+                })
+                .map(|(original, _)| *original)
+        });
+
+        match original {
+            // Posts which appear twice in the same feed are left alone.
+            Some(original) if original.0 != feed => {
+                duplicates.insert((feed, item), original);
+            }
+            Some(_) => {}
+            None => {
+                by_url.insert(url, (feed, item));
+                if words.len() >= MIN_TITLE_WORDS {
+                    titles.push(((feed, item), words));
+                }
+            }
+        }
+    }
+
+    // Record where the duplicates appeared, before removing them.
+    let mut also_in: HashMap<(usize, usize), Vec<String>> = HashMap::new();
+    for (&(feed, _), original) in &duplicates {
+        let names = also_in.entry(*original).or_default();
+        let name = feeds[feed].name().to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    for ((feed, item), mut names) in also_in {
+        names.sort();
+        let id = feeds[feed].items[item].id.clone();
+        feeds[feed].also_in.insert(id, names);
+    }
+
+    for (index, feed) in feeds.iter_mut().enumerate() {
+        let mut item = 0;
+        feed.items.retain(|_| {
+            let keep = !duplicates.contains_key(&(index, item));
+            item += 1;
+            keep
+        });
+    }
+}
+
+/// Normalize `url` so that urls which refer to the same page are equal: the scheme, a leading
+/// `www.`, trailing slashes, the fragment and tracking parameters (such as `utm_source`) are
+/// removed, and the remaining query parameters are sorted.
+pub fn normalize_url(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default().to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let port = url
+        .port()
+        .map(|port| format!(":{}", port))
+        .unwrap_or_default();
+    let path = url.path().trim_end_matches('/');
+
+    let mut query: Vec<_> = url
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_parameter(name))
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    query.sort();
+
+    if query.is_empty() {
+        format!("{}{}{}", host, port, path)
+    } else {
+        format!("{}{}{}?{}", host, port, path, query.join("&"))
+    }
+}
+
+/// Check whether the query parameter `name` is only used for tracking.
+fn is_tracking_parameter(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMETERS.contains(&name.as_str())
+}
+
+/// Get the words of `title`, in lowercase and without punctuation.
+fn title_words(title: &str) -> BTreeSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Get the fraction of the words of two titles which they have in common.
+fn similarity(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::FeedItem;
+    use chrono::DateTime;
+
+    fn item(title: &str, id: &str, timestamp: &str) -> FeedItem {
+        FeedItem {
+            title: title.to_string(),
+            id: id.to_string(),
+            timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap(),
+            published: None,
+            summary: None,
+            author: None,
+            categories: vec![],
+            content: None,
+            enclosures: vec![],
+        }
+    }
+
+    fn feeds() -> Vec<Feed> {
+        let planet = vec![
+            item(
+                "Announcing Rust 1.80.0",
+                "http://www.blog.example.com/rust-1.80?utm_source=planet",
+                "2024-07-25T12:00:00Z",
+            ),
+            item(
+                "Announcing the Rust 1.80.0 release",
+                "https://planet.example.com/posts/1",
+                "2024-07-25T13:00:00Z",
+            ),
+            item(
+                "Unrelated post",
+                "https://planet.example.com/posts/2",
+                "2024-07-25T13:00:00Z",
+            ),
+        ];
+        let blog = vec![
+            item(
+                "Announcing Rust 1.80.0 release",
+                "https://blog.example.com/rust-1.80/",
+                "2024-07-25T10:00:00Z",
+            ),
+            item("Older post", "/older", "2024-07-20T10:00:00Z"),
+        ];
+        let links = vec![item(
+            "rust 1.80",
+            "https://blog.example.com/rust-1.80",
+            "2024-07-26T10:00:00Z",
+        )];
+
+        [
+            ("Planet", "https://planet.example.com/feed.xml", planet),
+            ("Blog", "https://blog.example.com/feed.xml", blog),
+            ("Links", "https://links.example.com/feed.xml", links),
+        ]
+        .into_iter()
+        .map(|(name, url, items)| Feed::new(name.into(), url.into(), items).unwrap())
+        .collect()
+    }
+
+    fn titles(feed: &Feed) -> Vec<&str> {
+        feed.items.iter().map(|item| item.title.as_str()).collect()
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        let normalize = |url| normalize_url(&Url::parse(url).unwrap());
+        assert_eq!(
+            normalize("https://www.Blog.example.com/a/b/?utm_source=x&b=2&a=1&fbclid=3#top"),
+            "blog.example.com/a/b?a=1&b=2"
+        );
+        assert_eq!(
+            normalize("http://blog.example.com/a/b"),
+            "blog.example.com/a/b"
+        );
+        assert_eq!(
+            normalize("http://blog.example.com:8080/"),
+            "blog.example.com:8080"
+        );
+    }
+
+    #[test]
+    fn keeps_the_earliest_copy_of_a_post() {
+        let mut feeds = feeds();
+        dedupe(&mut feeds, false);
+
+        assert_eq!(
+            titles(&feeds[0]),
+            ["Announcing the Rust 1.80.0 release", "Unrelated post"]
+        );
+        assert_eq!(
+            titles(&feeds[1]),
+            ["Announcing Rust 1.80.0 release", "Older post"]
+        );
+        assert!(feeds[2].items.is_empty());
+        assert_eq!(feeds[1].also_in(&feeds[1].items[0]), ["Links", "Planet"]);
+    }
+
+    #[test]
+    fn compares_titles_if_asked_to() {
+        let mut feeds = feeds();
+        dedupe(&mut feeds, true);
+
+        assert_eq!(titles(&feeds[0]), ["Unrelated post"]);
+        assert_eq!(
+            titles(&feeds[1]),
+            ["Announcing Rust 1.80.0 release", "Older post"]
+        );
+        assert!(feeds[2].items.is_empty());
+    }
+
+    #[test]
+    fn measures_title_similarity() {
+        let a = title_words("Announcing Rust 1.80.0!");
+        assert_eq!(
+            a,
+            ["0", "1", "80", "announcing", "rust"]
+                .map(String::from)
+                .into()
+        );
+        assert_eq!(similarity(&a, &title_words("announcing rust 1.80.0")), 1.0);
+        assert_eq!(similarity(&a, &title_words("Something else")), 0.0);
+        assert_eq!(similarity(&BTreeSet::new(), &BTreeSet::new()), 0.0);
+    }
+}
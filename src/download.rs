@@ -0,0 +1,191 @@
+//! Functions for downloading the files enclosed with feed items, such as podcast episodes.
+
+use crate::{
+    error::*,
+    feed::{Enclosure, Feed},
+    http::HttpClient,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use termcolor::StandardStream;
+use url::Url;
+
+/// The extension of files which are still being downloaded.
+const PARTIAL_EXTENSION: &str = "part";
+
+/// The outcome of downloading an enclosure.
+enum Download {
+    /// The file was downloaded to the given path
+    Downloaded(PathBuf),
+
+    /// The file had already been downloaded to the given path
+    Skipped(PathBuf),
+}
+
+/// Download the enclosures of the `latest` most recent items in `feed` which have enclosures to
+/// `dir`. Files which have already been downloaded are skipped, and interrupted downloads are
+/// resumed. Failing to download a file does not stop the other files from being downloaded.
+pub fn download_enclosures(
+    stdout: &mut StandardStream,
+    client: &HttpClient,
+    feed: &Feed,
+    latest: usize,
+    dir: &Path,
+) -> Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let enclosures: Vec<&Enclosure> = feed
+        .items
+        .iter()
+        .filter(|item| !item.enclosures.is_empty())
+        .take(latest)
+        .flat_map(|item| &item.enclosures)
+        .collect();
+
+    if enclosures.is_empty() {
+        writeln!(stdout, "No enclosures found")?;
+        return Ok(());
+    }
+
+    for enclosure in enclosures {
+        match download_enclosure(client, enclosure, dir) {
+            Ok(Download::Downloaded(path)) => {
+                writeln!(stdout, "Downloaded '{}'", path.display())?;
+            }
+            Ok(Download::Skipped(path)) => {
+                writeln!(
+                    stdout,
+                    "Skipping '{}' as it has already been downloaded",
+                    path.display()
+                )?;
+            }
+            Err(error) => {
+                eprintln!("Failed to download '{}': {}", enclosure.url, error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Download a single enclosure to `dir`, resuming a previous download if there is one.
+fn download_enclosure(client: &HttpClient, enclosure: &Enclosure, dir: &Path) -> Result<Download> {
+    let path = dir.join(file_name_of(&enclosure.url)?);
+    if path.exists() {
+        return Ok(Download::Skipped(path));
+    }
+
+    let mut partial_path = path.clone().into_os_string();
+    partial_path.push(".");
+    partial_path.push(PARTIAL_EXTENSION);
+    let partial_path = PathBuf::from(partial_path);
+
+    let offset = fs::metadata(&partial_path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+
+    let mut headers = vec![];
+    if offset > 0 {
+        headers.push((String::from("Range"), format!("bytes={}-", offset)));
+    }
+
+    let response = match client.get(&enclosure.url, &headers) {
+        Ok(response) => response,
+        // The previous download was complete, but was interrupted before it could be renamed.
+        Err(Error::Http(error)) if offset > 0 && matches!(*error, ureq::Error::Status(416, _)) => {
+            fs::rename(&partial_path, &path)?;
+            return Ok(Download::Downloaded(path));
+        }
+        Err(error) => return Err(error),
+    };
+
+    // Servers which don't support ranges send the entire file instead.
+    let resume = offset > 0 && response.status() == 206;
+    let mut file = if resume {
+        OpenOptions::new().append(true).open(&partial_path)?
+    } else {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&partial_path)?
+    };
+
+    let start = if resume { offset } else { 0 };
+    let length = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok())
+        .or(enclosure.length);
+
+    let progress = match length {
+        Some(length) => ProgressBar::new(start + length).with_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({eta})")
+                .expect("progress bar template is valid"),
+        ),
+        None => ProgressBar::new_spinner(),
+    };
+    progress.set_position(start);
+
+    io::copy(
+        &mut response.into_reader(),
+        &mut progress.wrap_write(&mut file),
+    )?;
+    progress.finish_and_clear();
+
+    file.flush()?;
+    drop(file);
+    fs::rename(&partial_path, &path)?;
+
+    Ok(Download::Downloaded(path))
+}
+
+/// Get the name of the file to download `url` to, which is the last segment of its path with any
+/// characters which are not allowed in file names replaced. Files with the same name, such as
+/// `…/1234/audio.mp3` and `…/1235/audio.mp3`, are told apart by a hash of their url.
+fn file_name_of(url: &str) -> Result<String> {
+    let url = Url::parse(url)?;
+    let segment = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .unwrap_or_default();
+    let segment = percent_encoding::percent_decode_str(segment).decode_utf8_lossy();
+
+    let name: String = segment
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let hash = hash_of(url.as_str());
+    if name.trim_matches(['.', ' ']).is_empty() {
+        return Ok(format!(
+            "{}-{}",
+            url.host_str().unwrap_or("enclosure"),
+            hash
+        ));
+    }
+
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            Ok(format!("{}-{}.{}", stem, hash, extension))
+        }
+        _ => Ok(format!("{}-{}", name, hash)),
+    }
+}
+
+/// Get a short hash of `url`, using 32-bit FNV-1a. Unlike the hashers of the standard library, it
+/// never changes, so files which have already been downloaded are still found by later versions.
+fn hash_of(url: &str) -> String {
+    let hash = url.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+
+    format!("{:08x}", hash)
+}
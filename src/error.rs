@@ -2,41 +2,319 @@
 
 use thiserror::Error;
 
-pub(crate) type Result<R> = std::result::Result<R, Error>;
+/// The result type used throughout `gobbler`.
+pub type Result<R> = std::result::Result<R, Error>;
 
+/// The errors which can occur in `gobbler`.
 #[derive(Error, Debug)]
-pub(crate) enum Error {
+#[non_exhaustive]
+pub enum Error {
+    /// A call to a Windows API failed
     #[error("Windows error")]
     Windows(#[from] windows::core::Error),
 
+    /// The timestamp of a feed item could not be parsed
     #[error("Failed to parse updated time stamp")]
     ParseTimestamp(#[from] chrono::ParseError),
 
+    /// An I/O operation failed, such as reading the subscriptions file
     #[error("Unable to open database")]
     DatabaseRead(#[from] std::io::Error),
 
+    /// A subscription with the same name already exists
     #[error("Blog with name '{name}' already stored (old url: {old_url}, new url: {new_url})")]
     DuplicateName {
+        /// The name of the subscription
         name: String,
+        /// The url of the new subscription
         new_url: String,
+        /// The url of the existing subscription
         old_url: String,
     },
 
+    /// The url does not point to a valid feed
     #[error("'{0}' is not a valid RSS feed url")]
     InvalidRssFeedUrl(String),
 
+    /// The name of a subscription contains characters which cannot be stored in the subscriptions
+    /// file
+    #[error("'{0}' is not a valid name, as it is empty or contains commas, tabs or line breaks")]
+    InvalidFeedName(String),
+
+    /// There is no subscription with the given name
+    #[error("You are not subscribed to a feed named '{0}'")]
+    NotSubscribed(String),
+
+    /// A number could not be parsed
     #[error("Failed to parse to int")]
     ParseInt(#[from] std::num::ParseIntError),
 
+    /// A string returned by Windows could not be converted
     #[error("Failed to convert PWSTR to String")]
     PwstrToString(#[from] std::string::FromUtf16Error),
 
+    /// A path could not be created
     #[error("Failed to create PathBuf")]
     PathBufConvert(#[from] std::convert::Infallible),
 
+    /// The roaming application data directory could not be found
     #[error("Application data roaming directory not found")]
     AppDataRoamingDirNotFound,
 
+    /// The file is not a valid OPML file
+    #[error("'{0}' is not a valid OPML file")]
+    InvalidOpmlFile(String),
+
+    /// A point in time passed to `--since` or `--until` could not be parsed
+    #[error("'{0}' is not a valid date, duration or keyword")]
+    InvalidPointInTime(String),
+
+    /// A regular expression used to filter items is invalid
+    #[error("Invalid regular expression: {0}")]
+    InvalidRegex(#[from] regex::Error),
+
+    /// A rule contains characters which cannot be stored in the subscriptions file
+    #[error("'{0}' is not a valid rule, as it contains tabs or line breaks")]
+    InvalidRule(String),
+
+    /// A url could not be parsed
     #[error("Failed to parse url")]
     FailedToParseUrl(#[from] url::ParseError),
+
+    /// A JSON feed could not be parsed
+    #[error("Failed to parse JSON feed")]
+    ParseJsonFeed(#[from] serde_json::Error),
+
+    /// A JSON feed has an unsupported version
+    #[error("Unsupported JSON feed version '{0}'")]
+    UnsupportedJsonFeedVersion(String),
+
+    /// A time zone is not in the IANA time zone database
+    #[error("'{0}' is not a known time zone, such as Europe/Amsterdam or local")]
+    UnknownTimeZone(String),
+
+    /// A date format contains an invalid specifier
+    #[error("'{0}' is not a valid date format, such as %Y-%m-%d %H:%M")]
+    InvalidDateFormat(String),
+
+    /// An interval could not be parsed
+    #[error("'{0}' is not a valid interval, such as 30s, 15m, 2h or 1d")]
+    InvalidInterval(String),
+
+    /// A hook command contains characters which cannot be stored in the subscriptions file
+    #[error("'{0}' is not a valid hook command, as it contains tabs or line breaks")]
+    InvalidHookCommand(String),
+
+    /// A secret was given inline, rather than as a reference to where it is stored
+    #[error(
+        "Secrets must be referenced as env:VARIABLE, file:KEY or cmd:COMMAND, rather than given \
+         inline"
+    )]
+    InvalidSecret,
+
+    /// The value of `--basic` is not of the form `USER:SECRET`
+    #[error("Basic authentication must be given as USER:SECRET, such as alice:env:PASSWORD")]
+    InvalidBasicAuth,
+
+    /// The value of `--header` is not of the form `NAME: SECRET`
+    #[error("Headers must be given as 'NAME: SECRET', such as 'X-Api-Key: env:API_KEY'")]
+    InvalidHeader,
+
+    /// A feed url contains a password, which would be stored in the subscriptions file
+    #[error("Feed urls cannot contain a password, use --basic to authenticate instead")]
+    PasswordInUrl,
+
+    /// A secret could not be looked up
+    #[error("Failed to resolve the secret '{secret}': {reason}")]
+    ResolveSecret {
+        /// The reference to the secret, such as `env:API_KEY`
+        secret: String,
+        /// The reason the secret could not be looked up
+        reason: String,
+    },
+
+    /// A key in the credentials file contains characters which cannot be stored
+    #[error("'{0}' is not a valid key, as it is empty or contains tabs or line breaks")]
+    InvalidCredentialKey(String),
+
+    /// The credentials file can be read or written by other users
+    #[cfg_attr(
+        unix,
+        error(
+            "'{}' can be accessed by other users, restrict its permissions using chmod 600",
+            .0.display()
+        )
+    )]
+    #[cfg_attr(
+        not(unix),
+        error(
+            "'{}' can be accessed by other users, restrict its permissions so that only you can \
+             access it",
+            .0.display()
+        )
+    )]
+    InsecureCredentialsFile(std::path::PathBuf),
+
+    /// An error whose message contained a secret, which has been replaced by `***`
+    #[error("{0}")]
+    Redacted(String),
+
+    /// There is no webhook with the given name
+    #[error("There is no webhook named '{0}'")]
+    UnknownWebhook(String),
+
+    /// The url of a webhook is not a web url
+    #[error("'{0}' is not a valid webhook url, such as https://hooks.slack.com/services/...")]
+    InvalidWebhookUrl(String),
+
+    /// A webhook with the same name already exists
+    #[error("Webhook with name '{name}' already added (old url: {old_url}, new url: {new_url})")]
+    DuplicateWebhook {
+        /// The name of the webhook
+        name: String,
+        /// The url of the new webhook
+        new_url: String,
+        /// The url of the existing webhook
+        old_url: String,
+    },
+
+    /// The server could not be started, for example because the port is already in use
+    #[error("Failed to start the server: {0}")]
+    StartServer(Box<dyn std::error::Error + Send + Sync>),
+
+    /// The handler for Ctrl+C and termination signals could not be installed
+    #[error("Failed to install signal handler")]
+    SignalHandler(#[from] ctrlc::Error),
+
+    /// There is no post with the given id or url
+    #[error("There is no post with id '{0}'")]
+    UnknownPostId(String),
+
+    /// There is no post with the given number in the most recent listing
+    #[error("There is no post numbered {0}, show the posts using --number first")]
+    UnknownPostNumber(usize),
+
+    /// A feed has fewer posts than the number of the post which was asked for
+    #[error("'{name}' only has {count} posts")]
+    NotEnoughPosts {
+        /// The name of the feed
+        name: String,
+        /// The number of posts of the feed
+        count: usize,
+    },
+
+    /// A post has a url which is not a web url, which could start a program when it is opened
+    #[error("Refusing to open '{0}', as only http and https urls are opened")]
+    UnsafeUrl(String),
+
+    /// The web browser, or the configured opener, could not be started
+    #[error("Failed to run '{command}' to open the post: {source}")]
+    OpenBrowser {
+        /// The command used to open the post
+        command: String,
+        /// The reason the command could not be run
+        source: std::io::Error,
+    },
+
+    /// The terminal could not be used for the full-screen interface
+    #[error("Terminal error: {0}")]
+    Terminal(std::io::Error),
+
+    /// The archive of fetched posts could not be read or written
+    #[error("Failed to access the archive: {0}")]
+    Archive(#[from] rusqlite::Error),
+
+    /// The url of a proxy could not be parsed
+    #[error(
+        "'{0}' is not a valid proxy url, such as http://proxy.example.com:8080 or \
+         socks5://localhost:1080"
+    )]
+    InvalidProxyUrl(String),
+
+    /// The proxy could not be reached, or refused to connect to the server
+    #[error("Failed to connect through the proxy: {0}")]
+    ProxyConnect(Box<ureq::Error>),
+
+    /// The proxy rejected the credentials it was given, or asked for credentials
+    #[error("The proxy requires valid credentials: {0}")]
+    ProxyUnauthorized(Box<ureq::Error>),
+
+    /// A file with certificates to trust could not be loaded
+    #[error("Failed to load the CA bundle '{}': {reason}", .path.display())]
+    CaBundle {
+        /// The path to the file
+        path: std::path::PathBuf,
+        /// The reason the file could not be loaded
+        reason: String,
+    },
+
+    /// The TLS configuration could not be created
+    #[error("Failed to configure TLS: {0}")]
+    TlsConfig(String),
+
+    /// A local file, such as a feed, could not be read
+    #[error("Failed to read '{}': {source}", .path.display())]
+    ReadFeedFile {
+        /// The path to the file
+        path: std::path::PathBuf,
+        /// The reason the file could not be read
+        source: std::io::Error,
+    },
+
+    /// The command printing a feed failed
+    #[error("Feed command '{command}' failed: {reason}")]
+    FeedCommand {
+        /// The command which was run
+        command: String,
+        /// The reason the command failed
+        reason: String,
+    },
+
+    /// Only some of the scraping rules were given
+    #[error(
+        "Scraping a page requires --scrape-item, --scrape-title, --scrape-link and --scrape-date"
+    )]
+    IncompleteScrapeRules,
+
+    /// A scraping rule is not a valid CSS selector, or cannot be stored in the subscriptions file
+    #[error("'{0}' is not a valid CSS selector, such as 'article' or 'div.post > h2'")]
+    InvalidSelector(String),
+
+    /// The scraping rules found no complete posts on a page
+    #[error(
+        "No posts with a title, link and date were found on '{0}', check the rules using \
+         scrape-test"
+    )]
+    NothingScraped(String),
+
+    /// A feed which is fetched with credentials redirects to another server
+    #[error(
+        "'{from}' redirects to '{to}', which is on another server and is never sent the \
+         credentials of the feed"
+    )]
+    CredentialsRedirect {
+        /// The url which redirects
+        from: String,
+        /// The url it redirects to
+        to: String,
+    },
+
+    /// A server kept redirecting to other urls
+    #[error("Gave up after too many redirects, the last one to '{0}'")]
+    TooManyRedirects(String),
+
+    /// A HTTP request failed
+    #[error("HTTP request failed: {0}")]
+    Http(Box<ureq::Error>),
+}
+
+impl From<ureq::Error> for Error {
+    fn from(error: ureq::Error) -> Self {
+        // `ureq::Error` is rather large, so box it to keep `Result`s small.
+        match error.kind() {
+            ureq::ErrorKind::ProxyConnect => Error::ProxyConnect(Box::new(error)),
+            ureq::ErrorKind::ProxyUnauthorized => Error::ProxyUnauthorized(Box::new(error)),
+            _ => Error::Http(Box::new(error)),
+        }
+    }
 }
@@ -1,154 +1,571 @@
 //! Functions for interacting with RSS feeds and subscriptions to those feeds.
 
-use crate::error::*;
+use crate::{
+    archive::Archive,
+    auth::{check_url, Auth, Credentials, CREDENTIALS_FILE},
+    dates::DateFormatter,
+    error::*,
+    filter::{build_regex, FeedItemFilter, Filter},
+    html::to_plain_text,
+    http::HttpClient,
+    json_feed,
+    lines::{read_lines, write_lines},
+    listing::Listing,
+    marks::Marks,
+    scrape::ScrapeRules,
+    source,
+    terminal::{hyperlink, shorten_url, truncate, UrlStyle},
+    watch::parse_interval,
+    window::TimeWindow,
+    xml::get_attribute,
+};
 use chrono::{DateTime, FixedOffset, Utc};
 use indicatif::ParallelProgressIterator;
 use rayon::prelude::*;
+use serde::Serialize;
 use std::{
-    collections::{btree_map::Entry, BTreeMap},
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap},
     fmt::{self, Debug},
     fs::{self, OpenOptions},
-    io::{BufRead, BufReader, Write},
+    io::{BufRead, BufReader, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
 };
 use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+use unicode_width::UnicodeWidthStr;
 use url::Url;
 use windows::{
     core::HSTRING,
+    Data::Xml::Dom::XmlDocument,
     Foundation::Uri,
-    Web::Syndication::{SyndicationClient, SyndicationFormat, SyndicationItem},
+    Web::Syndication::{
+        SyndicationClient, SyndicationError, SyndicationErrorStatus, SyndicationFeed,
+        SyndicationFormat, SyndicationItem,
+    },
     Win32::UI::Shell::{FOLDERID_RoamingAppData, SHGetKnownFolderPath, KNOWN_FOLDER_FLAG},
 };
 
 /// The file used to store subscriptions.
-pub(crate) const DB_FILE: &str = "subscriptions.db";
+pub const DB_FILE: &str = "subscriptions.db";
 
 /// Represents a collection of RSS feed subscriptions.
-pub(crate) struct Database {
-    pub(crate) feeds: BTreeMap<String, String>,
+pub struct Database {
+    pub(crate) feeds: BTreeMap<String, Subscription>,
     path: PathBuf,
+
+    /// The archive in which all fetched items are stored, if it was opened
+    archive: Option<Mutex<Archive>>,
+}
+
+/// A subscription to a RSS feed.
+#[derive(Clone, Debug)]
+pub struct Subscription {
+    /// The url of the feed
+    pub url: String,
+
+    /// The tags used to categorize the feed
+    pub tags: BTreeSet<String>,
+
+    /// Only show items from the feed which match this regex
+    pub include: Option<String>,
+
+    /// Never show items from the feed which match this regex
+    pub exclude: Option<String>,
+
+    /// Poll the feed at this interval when watching feeds, such as `30m`
+    pub interval: Option<String>,
+
+    /// Run this command for every new item of the feed
+    pub hook: Option<String>,
+
+    /// The credentials the feed is fetched with
+    pub auth: Auth,
+
+    /// The rules used to scrape the posts from the page at the url, for sites without a feed
+    pub scrape: Option<ScrapeRules>,
+}
+
+impl Subscription {
+    /// Create a new [`Subscription`] to the feed at `url`, without any tags.
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            tags: BTreeSet::new(),
+            include: None,
+            exclude: None,
+            interval: None,
+            hook: None,
+            auth: Auth::default(),
+            scrape: None,
+        }
+    }
+
+    /// Parse a line from the subscriptions file, returning the name of the feed and the
+    /// subscription itself. Lines have the form `name,url`, optionally followed by tab separated
+    /// `key=value` attributes.
+    fn parse(line: &str) -> Option<(String, Self)> {
+        let mut fields = line.split('\t');
+        let (name, url) = fields.next()?.split_once(',')?;
+
+        let mut subscription = Self::new(url.to_string());
+        for field in fields {
+            match field.split_once('=') {
+                Some(("tags", value)) => subscription.tags = parse_tags(value),
+                Some(("include", value)) => subscription.include = Some(value.to_string()),
+                Some(("exclude", value)) => subscription.exclude = Some(value.to_string()),
+                Some(("interval", value)) => subscription.interval = Some(value.to_string()),
+                Some(("hook", value)) => subscription.hook = Some(value.to_string()),
+                Some((key, value)) if key.starts_with("scrape-") => subscription
+                    .scrape
+                    .get_or_insert_with(ScrapeRules::default)
+                    .parse_attribute(key, value),
+                Some((key, value)) => subscription.auth.parse_attribute(key, value),
+                None => {}
+            }
+        }
+        subscription.scrape = subscription.scrape.filter(ScrapeRules::is_complete);
+
+        Some((name.to_string(), subscription))
+    }
+
+    /// Check whether using the subscription runs commands, to fetch the feed, to look up its
+    /// credentials or for its new items.
+    pub fn runs_commands(&self) -> bool {
+        source::command_of(&self.url).is_some() || self.hook.is_some() || self.auth.runs_commands()
+    }
+
+    /// Fetch all items of the feed, scraping them from the page at the url if the subscription
+    /// has scraping rules, together with the interval at which the feed asks to be polled, see
+    /// [`fetch_items_with_ttl`].
+    pub fn fetch(
+        &self,
+        client: &HttpClient,
+        credentials: &Credentials,
+    ) -> Result<(Vec<FeedItem>, Option<Duration>)> {
+        match &self.scrape {
+            Some(rules) => Ok((rules.fetch_items(client, &self.url, credentials)?, None)),
+            None => fetch_items_with_ttl(client, &self.url, credentials),
+        }
+    }
+
+    /// Format the subscription as a line in the subscriptions file.
+    fn to_line(&self, name: &str) -> String {
+        let mut line = format!("{},{}", name, self.url);
+        if !self.tags.is_empty() {
+            line.push_str("\ttags=");
+            line.push_str(&self.tags.iter().cloned().collect::<Vec<_>>().join(","));
+        }
+        if let Some(include) = &self.include {
+            line.push_str("\tinclude=");
+            line.push_str(include);
+        }
+        if let Some(exclude) = &self.exclude {
+            line.push_str("\texclude=");
+            line.push_str(exclude);
+        }
+        if let Some(interval) = &self.interval {
+            line.push_str("\tinterval=");
+            line.push_str(interval);
+        }
+        if let Some(hook) = &self.hook {
+            line.push_str("\thook=");
+            line.push_str(hook);
+        }
+        for attribute in self.auth.to_attributes() {
+            line.push('\t');
+            line.push_str(&attribute);
+        }
+        for attribute in self.scrape.iter().flat_map(ScrapeRules::to_attributes) {
+            line.push('\t');
+            line.push_str(&attribute);
+        }
+
+        line
+    }
+}
+
+/// Parse a comma separated list of tags. Tags are case insensitive, so they are lowercased, and
+/// any whitespace within a tag is replaced by a `-`.
+pub fn parse_tags(tags: &str) -> BTreeSet<String> {
+    tags.split(',')
+        .map(normalize_tag)
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Normalize a single tag, see [`parse_tags`].
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
 }
 
 impl Database {
     /// Create a new [`Database`] by reading it from a file. If the file does not exist yet, it is
     /// created.
-    pub(crate) fn new() -> Result<Self> {
+    pub fn new() -> Result<Self> {
         let path = Self::get_subscriptions_db_file()?;
         let feeds = get_feeds_from_subscriptions_file(&path)?;
 
-        Ok(Self { feeds, path })
+        Ok(Self {
+            feeds,
+            path,
+            archive: None,
+        })
     }
 
     /// Create a new [`Database`] by reading it from the given file. If the file does not exist
     /// yet, it is created.
-    pub(crate) fn from_file(path: PathBuf) -> Result<Self> {
+    pub fn from_file(path: PathBuf) -> Result<Self> {
         let feeds = get_feeds_from_subscriptions_file(&path)?;
 
-        Ok(Self { feeds, path })
+        Ok(Self {
+            feeds,
+            path,
+            archive: None,
+        })
+    }
+
+    /// Open the archive next to the subscriptions file, after which every fetched item is stored
+    /// in it, see [`Archive`].
+    pub fn open_archive(&mut self) -> Result<()> {
+        self.archive = Some(Mutex::new(Archive::next_to(&self.path)?));
+
+        Ok(())
+    }
+
+    /// Store the items of `feed` in the archive, if it was opened, and set the time every item was
+    /// first published to the earliest time the archive knows of, see [`Archive::store`]. Failing
+    /// to do so only results in a warning, as it shouldn't get in the way of showing the feed.
+    pub fn archive(&self, feed: &mut Feed) {
+        let Some(archive) = &self.archive else {
+            return;
+        };
+
+        let result = archive
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .store(feed);
+        if let Err(error) = result {
+            eprintln!(
+                "WARNING: Failed to archive the posts of '{}': {}",
+                feed.name(),
+                error
+            );
+        }
+    }
+
+    /// Record an attempt to fetch the feed named `name` in the archive, if it was opened.
+    pub fn record_fetch(&self, name: &str, success: bool, latency: Duration) {
+        let Some(archive) = &self.archive else {
+            return;
+        };
+
+        let result = archive
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .record_fetch(name, Utc::now(), success, latency);
+        if let Err(error) = result {
+            eprintln!(
+                "WARNING: Failed to record fetching '{}' in the archive: {}",
+                name, error
+            );
+        }
     }
 
     /// Import subscriptions from `import_file` and store them in the database. This will currently
-    /// overwrite any existing subscriptions, so use at your own risk!
-    pub(crate) fn import_from(import_file: &str) -> Result<()> {
+    /// overwrite any existing subscriptions, so use at your own risk! Importing a file should never
+    /// be able to run commands, so subscriptions which would are skipped.
+    pub fn import_from(import_file: &str) -> Result<()> {
         let subscriptions_file = Self::get_subscriptions_db_file()?;
-        std::fs::copy(import_file, subscriptions_file)?;
+        let mut lines = read_lines(Path::new(import_file))?;
+        lines.retain(|line| match Subscription::parse(line) {
+            Some((name, subscription)) if subscription.runs_commands() => {
+                eprintln!(
+                    "WARNING: Skipping '{}', as feeds which run commands are never imported",
+                    name
+                );
+                false
+            }
+            _ => true,
+        });
+        write_lines(&subscriptions_file, lines)?;
 
         Ok(())
     }
 
     /// Add a feed subscription.
-    pub(crate) fn add(&mut self, name: String, url: String) -> Result<()> {
+    pub fn add(&mut self, name: String, subscription: Subscription) -> Result<()> {
+        // Lines of the subscriptions file start with `name,url`, followed by tab separated
+        // attributes.
+        if name.trim().is_empty() || name.contains([',', '\t', '\r', '\n']) {
+            return Err(Error::InvalidFeedName(name));
+        }
+        check_url(&subscription.url)?;
+        // Commands may contain anything, but not the separators of the subscriptions file.
+        if subscription.url.contains(['\t', '\r', '\n']) {
+            return Err(Error::InvalidRssFeedUrl(subscription.url));
+        }
+
         match self.feeds.entry(name.clone()) {
             Entry::Occupied(entry) => Err(Error::DuplicateName {
                 name,
-                new_url: url,
-                old_url: entry.get().to_string(),
+                new_url: subscription.url,
+                old_url: entry.get().url.to_string(),
             }),
             Entry::Vacant(entry) => {
-                let url = entry.insert(url);
-                append_entry_to_file(&self.path, &name, url)
+                let subscription = entry.insert(subscription);
+                append_entry_to_file(&self.path, &name, subscription)
             }
         }
     }
 
+    /// Replace the tags of a feed subscription. Returns `false` if there is no subscription with
+    /// the given name.
+    pub fn set_tags(&mut self, name: &str, tags: BTreeSet<String>) -> Result<bool> {
+        match self.feeds.get_mut(name) {
+            Some(subscription) => {
+                subscription.tags = tags;
+                self.save()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Replace the rules which the items of a feed subscription have to match in order to be
+    /// shown. Returns `false` if there is no subscription with the given name.
+    pub fn set_rules(
+        &mut self,
+        name: &str,
+        include: Option<String>,
+        exclude: Option<String>,
+    ) -> Result<bool> {
+        // Validate the rules before storing them, so that invalid rules are never saved. Every
+        // subscription is stored on a single line, with its attributes separated by tabs.
+        for rule in include.iter().chain(exclude.iter()) {
+            if rule.contains(['\t', '\n', '\r']) {
+                return Err(Error::InvalidRule(rule.clone()));
+            }
+            build_regex(rule)?;
+        }
+
+        match self.feeds.get_mut(name) {
+            Some(subscription) => {
+                subscription.include = include;
+                subscription.exclude = exclude;
+                self.save()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Replace the interval at which a feed subscription is polled when watching feeds. Returns
+    /// `false` if there is no subscription with the given name.
+    pub fn set_interval(&mut self, name: &str, interval: Option<String>) -> Result<bool> {
+        // Validate the interval before storing it, so that invalid intervals are never saved.
+        if let Some(interval) = &interval {
+            parse_interval(interval)?;
+        }
+
+        match self.feeds.get_mut(name) {
+            Some(subscription) => {
+                subscription.interval = interval;
+                self.save()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Replace the command which is run for every new item of a feed subscription. Returns `false`
+    /// if there is no subscription with the given name.
+    pub fn set_hook(&mut self, name: &str, hook: Option<String>) -> Result<bool> {
+        // Every subscription is stored on a single line, with its attributes separated by tabs.
+        if let Some(hook) = &hook {
+            if hook.contains(['\t', '\n', '\r']) {
+                return Err(Error::InvalidHookCommand(hook.clone()));
+            }
+        }
+
+        match self.feeds.get_mut(name) {
+            Some(subscription) => {
+                subscription.hook = hook;
+                self.save()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Replace the credentials a feed subscription is fetched with. Returns `false` if there is
+    /// no subscription with the given name.
+    pub fn set_auth(&mut self, name: &str, auth: Auth) -> Result<bool> {
+        match self.feeds.get_mut(name) {
+            Some(subscription) => {
+                subscription.auth = auth;
+                self.save()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     /// Remove a feed subscription.
-    pub(crate) fn remove(&mut self, name_to_remove: &str) -> Result<Option<String>> {
+    pub fn remove(&mut self, name_to_remove: &str) -> Result<Option<String>> {
         match self.feeds.remove(name_to_remove) {
-            Some(url) => {
-                let file = OpenOptions::new().read(true).open(&self.path)?;
-                let reader = BufReader::new(file);
-
-                let lines: Vec<String> = reader
-                    .lines()
-                    .map(|line| line.expect("Unable to read line from file"))
-                    .collect();
-
-                let mut file = OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .open(&self.path)?;
-
-                for line in lines {
-                    if let Some((name, _)) = line.split_once(',') {
-                        if name != name_to_remove {
-                            file.write_all(line.as_bytes())?;
-                            file.write_all(b"\n")?;
-                        }
-                    }
-                }
-
-                Ok(Some(url))
+            Some(subscription) => {
+                self.save()?;
+                Ok(Some(subscription.url))
             }
             None => Ok(None),
         }
     }
 
-    /// Write the list of subscriptions to the given [`StandardStream`].
-    pub(crate) fn print_subscriptions(&self, stdout: &mut StandardStream) -> Result<()> {
+    /// Write all subscriptions to the subscriptions file, replacing its current contents.
+    fn save(&self) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.path)?;
+
+        for (name, subscription) in &self.feeds {
+            file.write_all(subscription.to_line(name).as_bytes())?;
+            file.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the list of subscriptions which match `filter` to the given [`StandardStream`]. If
+    /// any of the subscriptions are tagged, the subscriptions are grouped by tag.
+    pub fn print_subscriptions(&self, stdout: &mut StandardStream, filter: &Filter) -> Result<()> {
         if self.is_empty() {
             writeln!(stdout, "No subscriptions added yet")?;
             return Ok(());
         }
 
-        for (name, url) in &self.feeds {
-            writeln!(stdout, "{} - {}", name, url)?;
+        let feeds: Vec<_> = self
+            .feeds
+            .iter()
+            .filter(|(name, subscription)| filter.matches(name, subscription))
+            .collect();
+
+        if feeds
+            .iter()
+            .all(|(_, subscription)| subscription.tags.is_empty())
+        {
+            for (name, subscription) in feeds {
+                writeln!(
+                    stdout,
+                    "{} - {}{}",
+                    name,
+                    subscription.url,
+                    describe_fetch(subscription)
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        let mut groups: BTreeMap<Option<&str>, Vec<_>> = BTreeMap::new();
+        for (name, subscription) in feeds {
+            if subscription.tags.is_empty() {
+                groups.entry(None).or_default().push((name, subscription));
+            }
+
+            for tag in &subscription.tags {
+                groups
+                    .entry(Some(tag.as_str()))
+                    .or_default()
+                    .push((name, subscription));
+            }
+        }
+
+        // Show untagged subscriptions last.
+        let untagged = groups.remove(&None);
+        for (tag, feeds) in groups
+            .into_iter()
+            .chain(untagged.map(|feeds| (None, feeds)))
+        {
+            stdout.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Color::Green)))?;
+            writeln!(stdout, "{}:", tag.unwrap_or("Untagged"))?;
+            stdout.reset()?;
+
+            for (name, subscription) in feeds {
+                writeln!(
+                    stdout,
+                    "    {} - {}{}",
+                    name,
+                    subscription.url,
+                    describe_fetch(subscription)
+                )?;
+            }
         }
 
         Ok(())
     }
 
+    /// Get the path to the file the subscriptions are stored in.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the path to the file secrets referenced as `file:KEY` are stored in.
+    pub fn credentials_file(&self) -> PathBuf {
+        self.path.with_file_name(CREDENTIALS_FILE)
+    }
+
+    /// Look up the secrets `subscription` is fetched with.
+    pub fn credentials(&self, subscription: &Subscription) -> Result<Credentials> {
+        subscription.auth.resolve(&self.credentials_file())
+    }
+
+    /// Get the feed subscriptions, by name.
+    pub fn subscriptions(&self) -> &BTreeMap<String, Subscription> {
+        &self.feeds
+    }
+
     /// Check whether any feed subscriptions have been added yet.
     fn is_empty(&self) -> bool {
         self.feeds.iter().peekable().peek().is_none()
     }
 
-    /// Collect all the feeds with items which were last updated after `since`. If
+    /// Collect all the feeds with items which were last updated within `window`. If
     /// `skip_empty_feeds` is `true`, empty feeds (feeds with no items in the specified timeframe)
-    /// are not returned. When a `filter_name` is passed in, any feeds whose name contains
-    /// `filter_name` will not be returned.
-    pub(crate) fn collect_feeds_with_items_since(
+    /// are not returned. Only feeds which match `filter` are returned, containing only the items
+    /// which match both `filter` and the rules of the feed.
+    pub fn collect_feeds_with_items_since(
         &self,
-        client: &SyndicationClient,
-        since: DateTime<Utc>,
+        client: &HttpClient,
+        window: &TimeWindow,
         skip_empty_feeds: bool,
-        filter_name: Option<String>,
+        filter: &Filter,
     ) -> Vec<Feed> {
-        let lowered_filter_name = filter_name.map(|filter_name| filter_name.to_lowercase());
-
         self.feeds
             .par_iter()
             .progress_count(self.feeds.len() as u64)
-            .filter_map(|(name, url)| {
-                if let Some(lowered_filter_name) = &lowered_filter_name {
-                    let lowered_name = name.to_lowercase();
-                    if !lowered_name.contains(lowered_filter_name) {
-                        return None;
-                    }
+            .filter_map(|(name, subscription)| {
+                if !filter.matches(name, subscription) {
+                    return None;
                 }
 
-                match get_items_from_feed(client, (name, url), since) {
+                let item_filter = match filter.item_filter_for(subscription) {
+                    Ok(item_filter) => item_filter,
+                    Err(error) => {
+                        eprintln!(
+                            "WARNING: Skipping '{}', as its rules are invalid: {}",
+                            name, error
+                        );
+                        return None;
+                    }
+                };
+                match self.get_items_from_feed(client, (name, subscription), window, &item_filter) {
                     Ok(feed) => {
                         if skip_empty_feeds && feed.items.is_empty() {
                             None
@@ -162,8 +579,59 @@ impl Database {
             .collect()
     }
 
+    /// Fetch all items of the feed with the given name which match `filter` and the rules of the
+    /// feed.
+    pub fn fetch_feed(&self, client: &HttpClient, name: &str, filter: &Filter) -> Result<Feed> {
+        self.fetch_feed_since(client, name, &TimeWindow::all_time(), filter)
+    }
+
+    /// Fetch the items of the feed with the given name which were last updated within `window`,
+    /// and which match `filter` and the rules of the feed.
+    pub fn fetch_feed_since(
+        &self,
+        client: &HttpClient,
+        name: &str,
+        window: &TimeWindow,
+        filter: &Filter,
+    ) -> Result<Feed> {
+        let (name, subscription) = self
+            .feeds
+            .get_key_value(name)
+            .ok_or_else(|| Error::NotSubscribed(name.to_string()))?;
+
+        self.get_items_from_feed(
+            client,
+            (name, subscription),
+            window,
+            &filter.item_filter_for(subscription)?,
+        )
+    }
+
+    /// Get all the items from a feed, returning only those items which were last updated within
+    /// `window` and which match `filter`. All items are archived, regardless of whether they are
+    /// returned.
+    fn get_items_from_feed(
+        &self,
+        client: &HttpClient,
+        (name, subscription): (&String, &Subscription),
+        window: &TimeWindow,
+        filter: &FeedItemFilter,
+    ) -> Result<Feed> {
+        let credentials = self.credentials(subscription)?;
+        let started = Instant::now();
+        let items = subscription.fetch(client, &credentials);
+        self.record_fetch(name, items.is_ok(), started.elapsed());
+
+        let (items, _) = items?;
+        let mut feed = Feed::new(name.clone(), subscription.url.clone(), items)?;
+        self.archive(&mut feed);
+        feed.retain_since(window, filter);
+
+        Ok(feed)
+    }
+
     /// Get the path to the feed subscriptions file.
-    pub(crate) fn get_subscriptions_db_file() -> Result<PathBuf> {
+    pub fn get_subscriptions_db_file() -> Result<PathBuf> {
         unsafe {
             let path = SHGetKnownFolderPath(
                 &FOLDERID_RoamingAppData as *const _,
@@ -187,8 +655,22 @@ impl Database {
     }
 }
 
+/// Describe how `subscription` is fetched when listing it, such as how it is authenticated,
+/// without revealing any secrets.
+fn describe_fetch(subscription: &Subscription) -> String {
+    let mut description = String::new();
+    if subscription.scrape.is_some() {
+        description.push_str(" (scraped)");
+    }
+    if !subscription.auth.is_empty() {
+        description.push_str(&format!(" (auth: {})", subscription.auth.describe()));
+    }
+
+    description
+}
+
 /// Get the feeds from the subscriptions listed in `file`.
-fn get_feeds_from_subscriptions_file(file: &Path) -> Result<BTreeMap<String, String>> {
+fn get_feeds_from_subscriptions_file(file: &Path) -> Result<BTreeMap<String, Subscription>> {
     let file = OpenOptions::new()
         .read(true)
         .write(true)
@@ -199,10 +681,10 @@ fn get_feeds_from_subscriptions_file(file: &Path) -> Result<BTreeMap<String, Str
     let reader = BufReader::new(file);
     for line in reader.lines() {
         let line = line?;
-        if let Some((name, url)) = line.split_once(',') {
+        if let Some((name, subscription)) = Subscription::parse(&line) {
             // ASSUME: When adding new feeds, they are checked for duplicates, so we don't need
             //         to check that here.
-            feeds.insert(name.to_string(), url.to_string());
+            feeds.insert(name, subscription);
         }
     }
 
@@ -210,135 +692,409 @@ fn get_feeds_from_subscriptions_file(file: &Path) -> Result<BTreeMap<String, Str
 }
 
 /// Add a new subscription to the subscriptions file.
-fn append_entry_to_file(path: &Path, name: &str, url: &str) -> Result<()> {
+fn append_entry_to_file(path: &Path, name: &str, subscription: &Subscription) -> Result<()> {
     OpenOptions::new()
         .read(true)
         .write(true)
         .append(true)
         .open(path)?
-        .write_all(format!("{},{}\n", name, url).as_bytes())?;
+        .write_all(format!("{}\n", subscription.to_line(name)).as_bytes())?;
 
     Ok(())
 }
 
-/// Get all the items from a feed, returning only those items which were last updated after `since`.
-fn get_items_from_feed(
-    client: &SyndicationClient,
-    (name, url): (&String, &String),
-    since: DateTime<Utc>,
-) -> Result<Feed> {
+/// Fetch all items from the feed at `url`, sending `credentials` along with the request. Both RSS
+/// and Atom feeds (through the Windows Syndication API), as well as JSON feeds are supported.
+/// Besides web servers, feeds are read from local files (`file://` urls) and from the output of
+/// commands (`exec:` urls), see [`source`].
+pub fn fetch_items(
+    client: &HttpClient,
+    url: &str,
+    credentials: &Credentials,
+) -> Result<Vec<FeedItem>> {
+    let (items, _) = fetch_items_with_ttl(client, url, credentials)?;
+
+    Ok(items)
+}
+
+/// Fetch all items from the feed at `url`, see [`fetch_items`], together with the interval at
+/// which the feed asks to be polled through its `ttl` or `sy:updatePeriod` element, if any.
+pub fn fetch_items_with_ttl(
+    client: &HttpClient,
+    url: &str,
+    credentials: &Credentials,
+) -> Result<(Vec<FeedItem>, Option<Duration>)> {
+    if let Some(command) = source::command_of(url) {
+        return parse_feed("", &source::run_command(command)?);
+    }
+    if source::is_file_url(url) {
+        return parse_feed("", &source::read_file(url)?);
+    }
+
+    // Feeds are left to the Syndication API of Windows where possible, as it uses the proxy and
+    // cache configured in Windows. It can't send credentials or parse JSON feeds, however, and
+    // knows nothing about the proxy and certificates `client` may be configured with.
+    if credentials.headers().is_empty() && client.is_plain() {
+        match retrieve_feed(url) {
+            Ok(feed) => return read_feed(&feed),
+            Err(error) if !is_invalid_xml(&error) => return Err(error),
+            Err(_) => {}
+        }
+    }
+
+    let response = client.get_with_credentials(url, credentials)?;
+    let content_type = response.content_type().to_string();
+    // Unlike `Response::into_string`, this does not limit the size of the feed.
+    let mut body = String::new();
+    response.into_reader().read_to_string(&mut body)?;
+
+    parse_feed(&content_type, &body)
+}
+
+/// Fetch the RSS or Atom feed at `url` through the Syndication API of Windows.
+fn retrieve_feed(url: &str) -> Result<SyndicationFeed> {
     let uri = Uri::CreateUri(&HSTRING::from(url))?;
-    let feed = client.RetrieveFeedAsync(&uri)?.get()?;
 
+    Ok(SyndicationClient::new()?.RetrieveFeedAsync(&uri)?.get()?)
+}
+
+/// Check whether `error` was returned by the Syndication API because a feed is not valid XML,
+/// which is the case for JSON feeds.
+fn is_invalid_xml(error: &Error) -> bool {
+    match error {
+        Error::Windows(error) => SyndicationError::GetStatus(error.code().0)
+            .is_ok_and(|status| status == SyndicationErrorStatus::InvalidXml),
+        _ => false,
+    }
+}
+
+/// Parse all items from `feed`, which is a RSS, Atom or JSON feed. Feeds which are read from
+/// anywhere but a web server, such as standard input, can be parsed this way.
+pub fn parse_items(feed: &str) -> Result<Vec<FeedItem>> {
+    let (items, _) = parse_feed("", feed)?;
+
+    Ok(items)
+}
+
+/// Parse all items from `body`, which was served with the given content type (if any), together
+/// with the interval at which the feed asks to be polled, see [`fetch_items_with_ttl`].
+fn parse_feed(content_type: &str, body: &str) -> Result<(Vec<FeedItem>, Option<Duration>)> {
+    if json_feed::is_json_feed(content_type, body) {
+        return Ok((json_feed::parse_items(body)?, None));
+    }
+
+    let feed = SyndicationFeed::new()?;
+    feed.Load(&HSTRING::from(body))?;
+
+    read_feed(&feed)
+}
+
+/// Read all items from `feed`, which is a RSS or Atom feed, together with the interval at which
+/// the feed asks to be polled. Items which can't be read, for example because they lack a valid
+/// timestamp, are skipped.
+fn read_feed(feed: &SyndicationFeed) -> Result<(Vec<FeedItem>, Option<Duration>)> {
     let format = feed.SourceFormat()?;
     if format != SyndicationFormat::Atom10 && format != SyndicationFormat::Rss20 {
         eprintln!("WARNING: Unsupported RSS feed format");
     }
 
-    let mut results = vec![];
-    for item in feed.Items()? {
-        let post = FeedItem::try_from((item, format)).unwrap();
-        if post.timestamp < since {
-            break;
-        }
+    let ttl = feed
+        .GetXmlDocument(format)
+        .ok()
+        .and_then(|xml| get_ttl(&xml));
+    let items = feed
+        .Items()?
+        .into_iter()
+        .filter_map(|item| FeedItem::try_from((item, format)).ok())
+        .collect();
+
+    Ok((items, ttl))
+}
 
-        results.push(post);
+/// Get the interval at which the feed in `xml` asks to be polled, from the `ttl` element of RSS
+/// feeds, or from the `sy:updatePeriod` and `sy:updateFrequency` elements of the syndication
+/// module.
+fn get_ttl(xml: &XmlDocument) -> Option<Duration> {
+    let text_of = |tag: &str| {
+        xml.GetElementsByTagName(&HSTRING::from(tag))
+            .and_then(|nodes| nodes.Item(0))
+            .and_then(|node| node.InnerText())
+            .map(|text| text.to_string_lossy().trim().to_string())
+            .ok()
+    };
+
+    // The `ttl` is given in minutes.
+    if let Some(ttl) = text_of("ttl").and_then(|ttl| ttl.parse::<u64>().ok()) {
+        return Some(Duration::from_secs(ttl * 60)).filter(|ttl| !ttl.is_zero());
     }
 
-    Ok(Feed::new(name.clone(), url.clone(), results)?)
+    let period: u64 = match text_of("sy:updatePeriod")?.as_str() {
+        "hourly" => 60 * 60,
+        "daily" => 60 * 60 * 24,
+        "weekly" => 60 * 60 * 24 * 7,
+        "monthly" => 60 * 60 * 24 * 30,
+        "yearly" => 60 * 60 * 24 * 365,
+        _ => return None,
+    };
+    let frequency = text_of("sy:updateFrequency")
+        .and_then(|frequency| frequency.parse::<u64>().ok())
+        .filter(|frequency| *frequency > 0)
+        .unwrap_or(1);
+
+    Some(Duration::from_secs(period / frequency))
 }
 
 /// A RSS feed
-pub(crate) struct Feed {
+pub struct Feed {
     /// The name of the feed
     name: String,
 
-    /// The base url of the feed
-    base_url: Url,
+    /// The url of the feed
+    url: String,
+
+    /// The base url of the feed, which feeds printed by commands or read from standard input
+    /// don't have
+    base_url: Option<Url>,
 
     /// The items in the feed
-    items: Vec<FeedItem>,
+    pub items: Vec<FeedItem>,
+
+    /// The names of the other feeds in which an item also appeared, by item id, see
+    /// [`crate::dedupe`]
+    pub(crate) also_in: HashMap<String, Vec<String>>,
 }
 
 impl Feed {
-    /// Create a new [`Feed`] instance with the given name and items.
-    fn new(name: String, url: String, items: Vec<FeedItem>) -> Result<Self> {
-        // Remove any path and query segments from the url, leaving the base url.
-        let mut url = Url::parse(&url)?;
-        match url.path_segments_mut() {
-            Ok(mut path) => {
+    /// Create a new [`Feed`] instance with the given name and items. Items whose url is relative
+    /// are skipped if the feed has no base url to resolve it against.
+    pub(crate) fn new(name: String, url: String, items: Vec<FeedItem>) -> Result<Self> {
+        // Remove any path and query segments from the url, leaving the base url. Urls such as
+        // `exec:` subscriptions have no path, and can't serve as a base url.
+        let mut base_url = Some(Url::parse(&url)?).filter(|url| !url.cannot_be_a_base());
+        if let Some(base_url) = &mut base_url {
+            if let Ok(mut path) = base_url.path_segments_mut() {
                 path.clear();
             }
-            Err(_) => {
-                unreachable!("validity of feed url is check when adding it")
-            }
+            base_url.set_query(None);
         }
-        url.set_query(None);
 
-        Ok(Self {
+        let mut feed = Self {
             name,
-            base_url: url,
+            url,
+            base_url,
             items,
+            also_in: HashMap::new(),
+        };
+        let count = feed.items.len();
+        let items = std::mem::take(&mut feed.items);
+        feed.items = items
+            .into_iter()
+            .filter(|item| feed.post_url(item).is_ok())
+            .collect();
+        if feed.items.len() < count {
+            eprintln!(
+                "WARNING: Skipping {} posts of '{}', as their urls are relative and the feed has \
+                 no url to resolve them against",
+                count - feed.items.len(),
+                feed.name
+            );
+        }
+
+        Ok(feed)
+    }
+
+    /// Read the feed at `source` without subscribing to it, keeping only the items which were last
+    /// updated within `window` and which match `filter`. Besides the urls which can be subscribed
+    /// to, `source` may be `-` to read the feed from standard input.
+    pub fn read(
+        client: &HttpClient,
+        source: &str,
+        window: &TimeWindow,
+        filter: &Filter,
+    ) -> Result<Self> {
+        let (name, url, items) = if source == "-" {
+            let mut feed = String::new();
+            std::io::stdin().read_to_string(&mut feed)?;
+            ("stdin", "stdin:", parse_items(&feed)?)
+        } else {
+            check_url(source)?;
+            let items = fetch_items(client, source, &Credentials::default())?;
+            (source, source, items)
+        };
+
+        let subscription = Subscription::new(url.to_string());
+        let mut feed = Self::new(name.to_string(), url.to_string(), items)?;
+        feed.retain_since(window, &filter.item_filter_for(&subscription)?);
+
+        Ok(feed)
+    }
+
+    /// Keep only the items which were last updated within `window` and which match `filter`.
+    fn retain_since(&mut self, window: &TimeWindow, filter: &FeedItemFilter) {
+        let mut results = vec![];
+        for post in std::mem::take(&mut self.items) {
+            if window.is_before(post.timestamp) {
+                break;
+            }
+
+            if !window.is_after(post.timestamp) && filter.matches(&post) {
+                results.push(post);
+            }
+        }
+        self.items = results;
+    }
+
+    /// Get the name of the feed.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the url of the feed.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Get the names of the other feeds in which `item`, an item of this feed, also appeared, if
+    /// duplicate items were removed from them.
+    pub fn also_in(&self, item: &FeedItem) -> &[String] {
+        self.also_in
+            .get(&item.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Get the url of the post `item`, which is an item of this feed.
+    pub fn post_url(&self, item: &FeedItem) -> Result<Url> {
+        // If the post url is relative, prepend the base url to make it absolute. Without a base
+        // url, only absolute urls can be used.
+        match (Url::parse(&item.id), &self.base_url) {
+            (Ok(post_url), _) if post_url.has_host() => Ok(post_url),
+            (_, Some(base_url)) => Ok(base_url.join(&item.id)?),
+            (post_url, None) => Ok(post_url?),
+        }
+    }
+
+    /// Get `item`, which is an item of this feed, in the form in which it is passed to other
+    /// programs.
+    pub fn export_item<'a>(&'a self, item: &'a FeedItem) -> Result<ExportedItem<'a>> {
+        Ok(ExportedItem {
+            feed: &self.name,
+            title: &item.title,
+            url: self.post_url(item)?.to_string(),
+            id: &item.id,
+            timestamp: item.timestamp.to_rfc3339(),
+            published: item.published.map(|published| published.to_rfc3339()),
+            author: item.author.as_deref(),
+            categories: &item.categories,
+            summary: item.summary.as_deref(),
+            content: item.content.as_deref(),
+            enclosures: item
+                .enclosures
+                .iter()
+                .map(|enclosure| ExportedEnclosure {
+                    url: &enclosure.url,
+                    mime_type: enclosure.mime_type.as_deref(),
+                    length: enclosure.length,
+                    duration_in_seconds: enclosure.duration.map(|duration| duration.as_secs()),
+                })
+                .collect(),
         })
     }
 
-    /// Writes the feed to the given [`StandardStream`].
-    pub(crate) fn print_colored(
+    /// Writes the feed to the given [`StandardStream`]. If a `listing` is given, every post which
+    /// is shown is added to it, and shown with its number in the listing.
+    pub fn print_colored(
         &self,
         stdout: &mut StandardStream,
-        weeks: i64,
-        limit: usize,
-        count_only: bool,
+        window: &TimeWindow,
+        options: &DisplayOptions,
+        mut listing: Option<&mut Listing>,
     ) -> Result<()> {
         stdout.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Color::Green)))?;
         writeln!(stdout, "{}:", self.name)?;
         stdout.reset()?;
 
         if self.items.is_empty() {
-            writeln!(
-                stdout,
-                "    No new posts in the last {}",
-                get_weeks_message(weeks)
-            )?;
+            writeln!(stdout, "    No new posts {}", window)?;
             return Ok(());
         }
 
-        if count_only {
+        if options.count_only {
             writeln!(
                 stdout,
-                "    {} post{} in the last {}",
+                "    {} post{} {}",
                 self.items.len(),
                 plural_postfix(self.items.len()),
-                get_weeks_message(weeks)
+                window
             )?;
             return Ok(());
         }
 
         let mut idx = 0;
-        let mut blue = ColorSpec::new();
-        let blue = blue.set_fg(Some(Color::Blue));
-        let mut yellow = ColorSpec::new();
-        let yellow = yellow.set_fg(Some(Color::Yellow));
+        let mut day = None;
         for item in &self.items {
-            stdout.set_color(blue)?;
-            write!(stdout, "    {}", item.timestamp.format("%c"))?;
-            stdout.reset()?;
+            if options.group_by_day {
+                let item_day = options.dates.day_of(item.timestamp);
+                if day != Some(item_day) {
+                    stdout.set_color(ColorSpec::new().set_bold(true))?;
+                    writeln!(stdout, "    {}", options.dates.format_day(item_day))?;
+                    stdout.reset()?;
+                    day = Some(item_day);
+                }
+            }
 
-            write!(stdout, " - {}", item.title)?;
+            let mut prefix = String::from(if options.group_by_day {
+                "      "
+            } else {
+                "    "
+            });
+            if let Some(listing) = listing.as_deref_mut() {
+                prefix.push_str(&format!("[{}] ", listing.push(self, item)?));
+            }
+            let timestamp = options.dates.format(item.timestamp);
+            let post_url = self.post_url(item)?;
+            let badges = Badges {
+                starred: options
+                    .marks
+                    .as_ref()
+                    .is_some_and(|marks| marks.is_starred(self.name(), item)),
+                updated: item.is_updated_within(window),
+            };
+            print_post(
+                stdout,
+                &prefix,
+                &timestamp,
+                &item.title,
+                &post_url,
+                badges,
+                options,
+            )?;
 
-            stdout.set_color(yellow)?;
+            let also_in = self.also_in(item);
+            if !also_in.is_empty() {
+                stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))?;
+                writeln!(stdout, "        also in: {}", also_in.join(", "))?;
+                stdout.reset()?;
+            }
 
-            // If the post url is relative, prepend the base url to make it absolute.
-            let post_url = match Url::parse(&item.id) {
-                Ok(post_url) if post_url.has_host() => post_url,
-                Ok(_) | Err(_) => self.base_url.join(&item.id)?,
-            };
+            for enclosure in &item.enclosures {
+                writeln!(stdout, "        Enclosure: {}", enclosure)?;
+            }
 
-            writeln!(stdout, " {}", post_url.to_string())?;
-            stdout.reset()?;
+            if options.summary {
+                let width = options
+                    .width
+                    .map(|width| {
+                        width
+                            .saturating_sub(8)
+                            .clamp(MIN_TITLE_WIDTH, SUMMARY_WIDTH)
+                    })
+                    .unwrap_or(SUMMARY_WIDTH);
+                item.print_summary(stdout, width)?;
+            }
 
             idx += 1;
-            if idx == limit {
+            if idx == options.limit {
                 break;
             }
         }
@@ -347,30 +1103,235 @@ impl Feed {
     }
 }
 
-fn plural_postfix(num: usize) -> &'static str {
-    if num == 1 {
-        ""
+/// Options which control how feeds are displayed.
+pub struct DisplayOptions {
+    /// Show at most this many items per feed
+    pub limit: usize,
+
+    /// Only show the number of items per feed
+    pub count_only: bool,
+
+    /// Show an excerpt of the summary of each item
+    pub summary: bool,
+
+    /// Make the titles and urls of items clickable, using OSC 8 hyperlinks
+    pub hyperlinks: bool,
+
+    /// How the urls of items are shown
+    pub urls: UrlStyle,
+
+    /// Truncate titles so that lines fit within this many columns, if set
+    pub width: Option<usize>,
+
+    /// Formats the timestamps of items
+    pub dates: DateFormatter,
+
+    /// Show the items under a heading for every day
+    pub group_by_day: bool,
+
+    /// Highlight the items which have been starred in these marks, if set
+    pub marks: Option<Marks>,
+}
+
+/// The fewest characters of a title which are shown when truncating titles to the width of the
+/// terminal.
+const MIN_TITLE_WIDTH: usize = 20;
+
+/// Shown in front of starred posts.
+const STAR: &str = "★ ";
+
+/// Shown after the timestamp of posts which were updated, rather than published, recently.
+const UPDATED: &str = " (updated)";
+
+/// What is marked about a post when it is shown.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Badges {
+    /// The post has been starred
+    pub(crate) starred: bool,
+
+    /// The post was first published before the posts which are shown, and is only shown because
+    /// it was updated since
+    pub(crate) updated: bool,
+}
+
+/// Writes a single line describing a post to the given [`StandardStream`]: `prefix`, followed by
+/// `timestamp`, `title` and the url of the post, as selected by `options`. Starred posts are
+/// marked with a star, and updated posts are marked as such after their timestamp.
+pub(crate) fn print_post(
+    stdout: &mut StandardStream,
+    prefix: &str,
+    timestamp: &str,
+    title: &str,
+    post_url: &Url,
+    badges: Badges,
+    options: &DisplayOptions,
+) -> Result<()> {
+    let url = match options.urls {
+        UrlStyle::Full => Some(post_url.to_string()),
+        UrlStyle::Short => Some(shorten_url(post_url)),
+        // Without hyperlinks, hiding the url would leave no way to get to the post.
+        UrlStyle::Hidden if !options.hyperlinks => Some(post_url.to_string()),
+        UrlStyle::Hidden => None,
+    };
+
+    // Truncate the title rather than letting the line wrap.
+    let title = match options.width {
+        Some(width) => {
+            let used = prefix.width()
+                + if badges.starred { STAR.width() } else { 0 }
+                + timestamp.width()
+                + if badges.updated { UPDATED.width() } else { 0 }
+                + " - ".width()
+                + url.as_ref().map(|url| url.width() + 1).unwrap_or(0);
+            truncate(title, width.saturating_sub(used).max(MIN_TITLE_WIDTH))
+        }
+        None => title.to_string(),
+    };
+
+    write!(stdout, "{}", prefix)?;
+    if badges.starred {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
+        write!(stdout, "{}", STAR)?;
+    }
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)))?;
+    write!(stdout, "{}", timestamp)?;
+    if badges.updated {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Magenta)))?;
+        write!(stdout, "{}", UPDATED)?;
+    }
+    stdout.reset()?;
+
+    if options.hyperlinks {
+        write!(stdout, " - {}", hyperlink(post_url.as_str(), &title))?;
     } else {
-        "s"
+        write!(stdout, " - {}", title)?;
     }
+
+    if let Some(url) = url {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+        if options.hyperlinks {
+            write!(stdout, " {}", hyperlink(post_url.as_str(), &url))?;
+        } else {
+            write!(stdout, " {}", url)?;
+        }
+        stdout.reset()?;
+    }
+    writeln!(stdout)?;
+
+    Ok(())
 }
 
-fn get_weeks_message(weeks: i64) -> String {
-    if weeks == 1 {
-        String::from("week")
+/// The width to which summaries are wrapped, unless the terminal is narrower.
+const SUMMARY_WIDTH: usize = 80;
+
+/// The maximum number of characters shown of a summary.
+const SUMMARY_LENGTH: usize = 240;
+
+/// Get an excerpt of `text`, which is all of `text` on a single line, truncated to at most
+/// [`SUMMARY_LENGTH`] characters.
+fn excerpt_of(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= SUMMARY_LENGTH {
+        return text;
+    }
+
+    let mut excerpt: String = text.chars().take(SUMMARY_LENGTH).collect();
+
+    // Don't cut off words halfway through.
+    if let Some(end) = excerpt.rfind(' ') {
+        excerpt.truncate(end);
+    }
+    excerpt.push('…');
+
+    excerpt
+}
+
+/// Wrap `text` into lines of at most `width` columns, breaking lines between words. Words
+/// longer than `width` are put on a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.width() + 1 + word.width() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+fn plural_postfix(num: usize) -> &'static str {
+    if num == 1 {
+        ""
     } else {
-        format!("{} weeks", weeks)
+        "s"
     }
 }
 
 /// An item in a [`Feed`]
-pub(crate) struct FeedItem {
+pub struct FeedItem {
     /// The title of the item
-    title: String,
+    pub title: String,
     /// The id of the item
-    id: String,
-    /// The timestamp of the item
-    timestamp: DateTime<FixedOffset>,
+    pub id: String,
+    /// The timestamp of the item, which is the time it was last updated
+    pub timestamp: DateTime<FixedOffset>,
+    /// The time the item was first published, if known
+    pub published: Option<DateTime<FixedOffset>>,
+    /// The summary of the item, as plain text
+    pub summary: Option<String>,
+    /// The name of the (first) author of the item
+    pub author: Option<String>,
+    /// The categories of the item
+    pub categories: Vec<String>,
+    /// The full content of the item, as plain text
+    pub content: Option<String>,
+    /// The files enclosed with the item, such as podcast episodes
+    pub enclosures: Vec<Enclosure>,
+}
+
+impl FeedItem {
+    /// Check whether the item was first published before `window`, and only falls within it
+    /// because it was updated since.
+    pub fn is_updated_within(&self, window: &TimeWindow) -> bool {
+        self.published.is_some_and(|published| {
+            window.is_before(published) && !window.is_before(self.timestamp)
+        })
+    }
+
+    /// Writes the author, categories and an excerpt of the summary of the item to the given
+    /// [`StandardStream`], wrapping the excerpt to `width` characters.
+    fn print_summary(&self, stdout: &mut StandardStream, width: usize) -> Result<()> {
+        let mut byline = vec![];
+        if let Some(author) = &self.author {
+            byline.push(format!("by {}", author));
+        }
+        if !self.categories.is_empty() {
+            byline.push(self.categories.join(", "));
+        }
+        if !byline.is_empty() {
+            stdout.set_color(ColorSpec::new().set_italic(true))?;
+            writeln!(stdout, "        {}", byline.join(" · "))?;
+            stdout.reset()?;
+        }
+
+        if let Some(excerpt) = self.summary.as_ref().or(self.content.as_ref()) {
+            for line in wrap(&excerpt_of(excerpt), width) {
+                writeln!(stdout, "        {}", line)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl TryFrom<(SyndicationItem, SyndicationFormat)> for FeedItem {
@@ -386,6 +1347,21 @@ impl TryFrom<(SyndicationItem, SyndicationFormat)> for FeedItem {
             .InnerText()?
             .to_string_lossy();
 
+        // Atom has a separate element for when an item was first published, RSS only has that.
+        let text_of = |tag: &str| {
+            xml.GetElementsByTagName(&HSTRING::from(tag))
+                .and_then(|nodes| nodes.Item(0))
+                .and_then(|node| node.InnerText())
+                .map(|text| text.to_string_lossy().trim().to_string())
+                .ok()
+        };
+        let published = text_of("published")
+            .and_then(|published| DateTime::parse_from_rfc3339(&published).ok())
+            .or_else(|| {
+                text_of("pubDate")
+                    .and_then(|published| DateTime::parse_from_rfc2822(&published).ok())
+            });
+
         let mut id = item.Id()?.to_string_lossy();
         if !is_valid_url(&id) {
             id = xml
@@ -397,14 +1373,220 @@ impl TryFrom<(SyndicationItem, SyndicationFormat)> for FeedItem {
                 .to_string_lossy();
         }
 
+        let summary = item
+            .Summary()
+            .and_then(|summary| summary.Text())
+            .map(|summary| to_plain_text(&summary.to_string_lossy()))
+            .ok()
+            .filter(|summary| !summary.is_empty());
+
+        let content = item
+            .Content()
+            .and_then(|content| content.Text())
+            .map(|content| to_plain_text(&content.to_string_lossy()))
+            .ok()
+            .filter(|content| !content.is_empty());
+
+        let categories = item
+            .Categories()?
+            .into_iter()
+            .filter_map(|category| {
+                // The label is meant for display, but is optional, unlike the term.
+                let label = category.Label().ok().filter(|label| !label.is_empty());
+                label.or_else(|| category.Term().ok())
+            })
+            .map(|category| category.to_string_lossy())
+            .filter(|category| !category.is_empty())
+            .collect();
+
+        let enclosures = get_enclosures(&item, &xml)?;
+
+        let author = item
+            .Authors()?
+            .into_iter()
+            .next()
+            .and_then(|author| author.Name().ok())
+            .map(|author| author.to_string_lossy())
+            .filter(|author| !author.is_empty());
+
         Ok(Self {
             title: item.Title()?.Text()?.to_string_lossy(),
             id,
             timestamp: DateTime::parse_from_rfc3339(&timestamp)?,
+            published,
+            summary,
+            author,
+            categories,
+            content,
+            enclosures,
         })
     }
 }
 
+/// Get the files enclosed with `item`, from its links as well as from any `enclosure` and
+/// `media:content` elements in its `xml`.
+fn get_enclosures(item: &SyndicationItem, xml: &XmlDocument) -> Result<Vec<Enclosure>> {
+    let mut enclosures = vec![];
+    for link in item.Links()? {
+        let is_enclosure = link
+            .Relationship()
+            .map(|relationship| relationship.to_string_lossy() == "enclosure")
+            .unwrap_or(false);
+        if !is_enclosure {
+            continue;
+        }
+
+        if let Ok(url) = link.Uri().and_then(|uri| uri.AbsoluteUri()) {
+            enclosures.push(Enclosure {
+                url: url.to_string_lossy(),
+                mime_type: link
+                    .MediaType()
+                    .map(|mime_type| mime_type.to_string_lossy())
+                    .ok()
+                    .filter(|mime_type| !mime_type.is_empty()),
+                length: link
+                    .Length()
+                    .ok()
+                    .filter(|length| *length > 0)
+                    .map(u64::from),
+                duration: None,
+            });
+        }
+    }
+
+    for (tag, length_attribute) in [("enclosure", "length"), ("media:content", "fileSize")] {
+        for node in xml.GetElementsByTagName(&HSTRING::from(tag))? {
+            let Some(url) = get_attribute(&node, "url") else {
+                continue;
+            };
+
+            let duration = get_attribute(&node, "duration").and_then(|d| parse_duration(&d));
+            match enclosures.iter_mut().find(|enclosure| enclosure.url == url) {
+                Some(enclosure) => {
+                    enclosure.duration = enclosure.duration.or(duration);
+                }
+                None => enclosures.push(Enclosure {
+                    url,
+                    mime_type: get_attribute(&node, "type"),
+                    length: get_attribute(&node, length_attribute)
+                        .and_then(|length| length.parse().ok())
+                        .filter(|length| *length > 0),
+                    duration,
+                }),
+            }
+        }
+    }
+
+    // The duration of a podcast episode is stored on the item rather than on the enclosure.
+    let duration = xml
+        .GetElementsByTagName(&HSTRING::from("itunes:duration"))?
+        .Item(0)
+        .and_then(|node| node.InnerText())
+        .ok()
+        .and_then(|duration| parse_duration(&duration.to_string_lossy()));
+    for enclosure in &mut enclosures {
+        enclosure.duration = enclosure.duration.or(duration);
+    }
+
+    Ok(enclosures)
+}
+
+/// Parse a duration in the formats used by `itunes:duration`, which are `HH:MM:SS`, `MM:SS` or a
+/// number of seconds.
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let mut seconds: u64 = 0;
+    for part in duration.trim().split(':') {
+        let part = part
+            .parse::<f64>()
+            .ok()
+            .filter(|part| *part < u64::MAX as f64)? as u64;
+        seconds = seconds.checked_mul(60)?.checked_add(part)?;
+    }
+
+    Some(Duration::from_secs(seconds)).filter(|duration| !duration.is_zero())
+}
+
+/// A file enclosed with a [`FeedItem`], such as a podcast episode.
+pub struct Enclosure {
+    /// The url of the file
+    pub url: String,
+    /// The MIME type of the file
+    pub mime_type: Option<String>,
+    /// The size of the file in bytes
+    pub length: Option<u64>,
+    /// The duration of the media in the file
+    pub duration: Option<Duration>,
+}
+
+impl fmt::Display for Enclosure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.url)?;
+
+        let mut details = vec![];
+        if let Some(mime_type) = &self.mime_type {
+            details.push(mime_type.clone());
+        }
+        if let Some(length) = self.length {
+            details.push(format!("{:.1} MB", length as f64 / 1_000_000.0));
+        }
+        if let Some(duration) = self.duration {
+            let seconds = duration.as_secs();
+            details.push(format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ));
+        }
+
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A [`FeedItem`] in the form in which it is passed to other programs, for example as JSON.
+#[derive(Serialize)]
+pub struct ExportedItem<'a> {
+    /// The name of the feed the item belongs to
+    pub feed: &'a str,
+    /// The title of the item
+    pub title: &'a str,
+    /// The (absolute) url of the item
+    pub url: String,
+    /// The id of the item
+    pub id: &'a str,
+    /// The timestamp of the item, in RFC 3339 format
+    pub timestamp: String,
+    /// The time the item was first published, in RFC 3339 format, if known
+    pub published: Option<String>,
+    /// The name of the (first) author of the item
+    pub author: Option<&'a str>,
+    /// The categories of the item
+    pub categories: &'a [String],
+    /// The summary of the item, as plain text
+    pub summary: Option<&'a str>,
+    /// The full content of the item, as plain text
+    pub content: Option<&'a str>,
+    /// The files enclosed with the item
+    pub enclosures: Vec<ExportedEnclosure<'a>>,
+}
+
+/// An [`Enclosure`] in the form in which it is passed to other programs.
+#[derive(Serialize)]
+pub struct ExportedEnclosure<'a> {
+    /// The url of the file
+    pub url: &'a str,
+    /// The MIME type of the file
+    pub mime_type: Option<&'a str>,
+    /// The size of the file in bytes
+    pub length: Option<u64>,
+    /// The duration of the media in the file, in seconds
+    pub duration_in_seconds: Option<u64>,
+}
+
 impl Debug for FeedItem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -421,3 +1603,67 @@ impl Debug for FeedItem {
 fn is_valid_url(url: &str) -> bool {
     url.starts_with("http")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("62:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration(" 3723 "), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_duration("90.5"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("0:00"), None);
+        assert_eq!(parse_duration("1:xx"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn rejects_durations_which_overflow() {
+        assert_eq!(parse_duration("99999999999999999999:00"), None);
+        assert_eq!(parse_duration("1e300"), None);
+        assert_eq!(parse_duration(&["9999999999"; 7].join(":")), None);
+    }
+
+    #[test]
+    fn formats_enclosures() {
+        let enclosure = Enclosure {
+            url: String::from("https://example.com/episode.mp3"),
+            mime_type: Some(String::from("audio/mpeg")),
+            length: Some(45_200_000),
+            duration: parse_duration("1:02:03"),
+        };
+        assert_eq!(
+            enclosure.to_string(),
+            "https://example.com/episode.mp3 (audio/mpeg, 45.2 MB, 1:02:03)"
+        );
+
+        let enclosure = Enclosure {
+            url: String::from("https://example.com/episode.mp3"),
+            mime_type: None,
+            length: None,
+            duration: None,
+        };
+        assert_eq!(enclosure.to_string(), "https://example.com/episode.mp3");
+    }
+
+    #[test]
+    fn round_trips_subscriptions() {
+        let mut subscription = Subscription::new(String::from("https://example.com/feed.xml"));
+        subscription.tags = parse_tags("Rust, news");
+        subscription.include = Some(String::from("(?i)release"));
+        subscription.interval = Some(String::from("2h"));
+
+        let line = subscription.to_line("Blog");
+        let (name, parsed) = Subscription::parse(&line).unwrap();
+        assert_eq!(name, "Blog");
+        assert_eq!(parsed.url, subscription.url);
+        assert_eq!(parsed.tags, subscription.tags);
+        assert_eq!(parsed.include, subscription.include);
+        assert_eq!(parsed.exclude, None);
+        assert_eq!(parsed.interval, subscription.interval);
+
+        assert!(Subscription::parse("no url").is_none());
+    }
+}
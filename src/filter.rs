@@ -0,0 +1,165 @@
+//! Filters which control which feeds and feed items are shown.
+
+use crate::{
+    error::*,
+    feed::{normalize_tag, FeedItem, Subscription},
+};
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+
+/// Criteria which a feed has to match in order to be shown.
+pub struct Filter {
+    /// Only show feeds whose (lowercased) name contains this name
+    name: Option<String>,
+
+    /// Only show feeds with at least one of these tags
+    tags: Vec<String>,
+
+    /// Never show feeds with any of these tags
+    exclude_tags: Vec<String>,
+
+    /// The filter applied to the items of every feed
+    items: ItemFilter,
+}
+
+impl Filter {
+    /// Create a new [`Filter`]. Feeds are matched against `name` case insensitively, and a feed
+    /// has to be tagged with at least one of `tags` (if any are given) and with none of
+    /// `exclude_tags`.
+    pub fn new(
+        name: Option<String>,
+        tags: Vec<String>,
+        exclude_tags: Vec<String>,
+        items: ItemFilter,
+    ) -> Self {
+        Self {
+            name: name.map(|name| name.to_lowercase()),
+            tags: tags.iter().map(|tag| normalize_tag(tag)).collect(),
+            exclude_tags: exclude_tags.iter().map(|tag| normalize_tag(tag)).collect(),
+            items,
+        }
+    }
+
+    /// Check whether the feed with the given name and subscription matches this filter.
+    pub fn matches(&self, name: &str, subscription: &Subscription) -> bool {
+        if let Some(filter_name) = &self.name {
+            if !name.to_lowercase().contains(filter_name) {
+                return false;
+            }
+        }
+
+        if !self.tags.is_empty() && !self.tags.iter().any(|tag| subscription.tags.contains(tag)) {
+            return false;
+        }
+
+        !self
+            .exclude_tags
+            .iter()
+            .any(|tag| subscription.tags.contains(tag))
+    }
+
+    /// Get the filter for the items of the feed with the given subscription. This combines the
+    /// item filter which applies to every feed with the rules stored with the subscription.
+    pub fn item_filter_for(&self, subscription: &Subscription) -> Result<FeedItemFilter<'_>> {
+        let rules = ItemFilter::new(
+            subscription.include.as_deref(),
+            subscription.exclude.as_deref(),
+            self.items.fields.clone(),
+        )?;
+
+        Ok(FeedItemFilter {
+            global: &self.items,
+            rules,
+        })
+    }
+}
+
+/// The fields of a [`FeedItem`] which an [`ItemFilter`] can match against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ItemField {
+    /// The title of the item
+    Title,
+    /// The summary of the item
+    Summary,
+    /// The name of the author of the item
+    Author,
+}
+
+/// Criteria which a feed item has to match in order to be shown.
+pub struct ItemFilter {
+    /// Only show items which match this regex
+    include: Option<Regex>,
+
+    /// Never show items which match this regex
+    exclude: Option<Regex>,
+
+    /// The fields of an item which are matched against
+    fields: Vec<ItemField>,
+}
+
+impl ItemFilter {
+    /// Create a new [`ItemFilter`] from the `include` and `exclude` regexes, which are matched case
+    /// insensitively against the given `fields` of an item. If no fields are given, only the title
+    /// is matched against.
+    pub fn new(
+        include: Option<&str>,
+        exclude: Option<&str>,
+        fields: Vec<ItemField>,
+    ) -> Result<Self> {
+        let fields = if fields.is_empty() {
+            vec![ItemField::Title]
+        } else {
+            fields
+        };
+
+        Ok(Self {
+            include: include.map(build_regex).transpose()?,
+            exclude: exclude.map(build_regex).transpose()?,
+            fields,
+        })
+    }
+
+    /// Check whether `item` matches this filter.
+    pub fn matches(&self, item: &FeedItem) -> bool {
+        let is_match = |regex: &Regex| {
+            self.fields.iter().any(|field| {
+                let text = match field {
+                    ItemField::Title => Some(item.title.as_str()),
+                    ItemField::Summary => item.summary.as_deref(),
+                    ItemField::Author => item.author.as_deref(),
+                };
+
+                text.map(|text| regex.is_match(text)).unwrap_or(false)
+            })
+        };
+
+        if let Some(include) = &self.include {
+            if !is_match(include) {
+                return false;
+            }
+        }
+
+        !self.exclude.as_ref().map(is_match).unwrap_or(false)
+    }
+}
+
+/// The item filter for a single feed, see [`Filter::item_filter_for`].
+pub struct FeedItemFilter<'a> {
+    /// The filter which applies to every feed
+    global: &'a ItemFilter,
+
+    /// The rules stored with the subscription of the feed
+    rules: ItemFilter,
+}
+
+impl FeedItemFilter<'_> {
+    /// Check whether `item` matches both the global filter and the rules of the feed.
+    pub fn matches(&self, item: &FeedItem) -> bool {
+        self.global.matches(item) && self.rules.matches(item)
+    }
+}
+
+/// Build a case insensitive [`Regex`] from `pattern`.
+pub(crate) fn build_regex(pattern: &str) -> Result<Regex> {
+    Ok(RegexBuilder::new(pattern).case_insensitive(true).build()?)
+}
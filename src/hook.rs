@@ -0,0 +1,311 @@
+//! Running commands (hooks) for new feed items, so that `gobbler` can be wired to other tools.
+
+use crate::{
+    error::*,
+    feed::{ExportedItem, Feed, FeedItem},
+    lines::{append_lines, escape, read_log},
+    watch::format_interval,
+};
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The file used to keep track of the items for which the hooks have run, which is stored next to
+/// the subscriptions file.
+const HOOK_LOG_FILE: &str = "hooks.log";
+
+/// The number of items of every feed for which it is remembered that the hooks have run. Feeds
+/// contain far fewer items than this, so older items have long left their feed.
+const RUNS_PER_FEED: usize = 1000;
+
+/// How often to check whether a hook has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait for the error output of a hook after it finished. Processes started by the
+/// hook may keep its error output open long after the hook itself has finished.
+const STDERR_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Runs hooks for new feed items. Every hook is run through the shell, with the fields of the
+/// item passed as environment variables, and the item passed as JSON on standard input.
+pub struct HookRunner {
+    /// The command which is run for the new items of every feed
+    command: Option<String>,
+
+    /// The maximum number of hooks which run at the same time
+    jobs: usize,
+
+    /// The time after which a hook is stopped
+    timeout: Duration,
+}
+
+/// A hook which failed to run successfully.
+pub struct HookFailure {
+    /// The command of the hook
+    pub command: String,
+
+    /// The name of the feed the item belongs to
+    pub feed: String,
+
+    /// The title of the item the hook was run for
+    pub title: String,
+
+    /// The reason the hook failed
+    pub reason: String,
+}
+
+impl fmt::Display for HookFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Hook '{}' failed for '{}' from '{}': {}",
+            self.command, self.title, self.feed, self.reason
+        )
+    }
+}
+
+impl HookRunner {
+    /// Create a new [`HookRunner`] which runs `command` (if any) for the new items of every feed,
+    /// running at most `jobs` hooks at the same time and stopping hooks after `timeout`.
+    pub fn new(command: Option<String>, jobs: usize, timeout: Duration) -> Self {
+        Self {
+            command,
+            jobs: jobs.max(1),
+            timeout,
+        }
+    }
+
+    /// Run the hooks for every item in `feed` for which `log` shows they haven't run yet. Both
+    /// the command of the runner and `feed_hook`, the command of the feed itself, are run. Items
+    /// for which every hook succeeded are recorded in `log`, the others are retried the next time.
+    /// Returns the hooks which failed.
+    pub fn run(&self, log: &mut HookLog, feed: &Feed, feed_hook: Option<&str>) -> Vec<HookFailure> {
+        let commands: Vec<&str> = self
+            .command
+            .as_deref()
+            .into_iter()
+            .chain(feed_hook)
+            .collect();
+        let items: Vec<&FeedItem> = feed
+            .items
+            .iter()
+            .filter(|item| !log.contains(feed, item))
+            .collect();
+        let jobs: Vec<(&str, &FeedItem)> = items
+            .iter()
+            .flat_map(|item| commands.iter().map(move |command| (*command, *item)))
+            .collect();
+        if jobs.is_empty() {
+            return vec![];
+        }
+
+        let workers = self.jobs.min(jobs.len());
+        let jobs = Mutex::new(jobs.into_iter());
+        let failures = Mutex::new(vec![]);
+        let failed = Mutex::new(HashSet::new());
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let Some((command, item)) = jobs.lock().unwrap().next() else {
+                        break;
+                    };
+
+                    if let Err(reason) = self.run_hook(command, feed, item) {
+                        failed.lock().unwrap().insert(&item.id);
+                        failures.lock().unwrap().push(HookFailure {
+                            command: command.to_string(),
+                            feed: feed.name().to_string(),
+                            title: item.title.clone(),
+                            reason,
+                        });
+                    }
+                });
+            }
+        });
+
+        let failed = failed.into_inner().unwrap();
+        let succeeded: Vec<_> = items
+            .into_iter()
+            .filter(|item| !failed.contains(&item.id))
+            .collect();
+        if let Err(error) = log.record(feed, &succeeded) {
+            eprintln!(
+                "WARNING: Failed to record the hooks which ran for '{}', they run again the next \
+                 time: {}",
+                feed.name(),
+                error
+            );
+        }
+
+        failures.into_inner().unwrap()
+    }
+
+    /// Run `command` for `item`, an item of `feed`, returning why it failed if it did.
+    fn run_hook(
+        &self,
+        command: &str,
+        feed: &Feed,
+        item: &FeedItem,
+    ) -> std::result::Result<(), String> {
+        let item = feed.export_item(item).map_err(|error| error.to_string())?;
+        let json = serde_json::to_vec(&item).map_err(|error| error.to_string())?;
+
+        let mut child = shell(command)
+            .envs(environment(&item))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("failed to start ({})", error))?;
+
+        // Write the input and read the error output on their own threads, so that a hook which
+        // doesn't read its input, or writes a lot of output, can't block `gobbler`.
+        if let Some(mut stdin) = child.stdin.take() {
+            thread::spawn(move || {
+                // Hooks are free to ignore their input, so failing to write it is fine.
+                let _ = stdin.write_all(&json);
+            });
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        if let Some(mut stderr) = child.stderr.take() {
+            thread::spawn(move || {
+                let mut output = String::new();
+                let _ = stderr.read_to_string(&mut output);
+                let _ = sender.send(output);
+            });
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("timed out after {}", format_interval(self.timeout)));
+                }
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(error) => return Err(error.to_string()),
+            }
+        };
+
+        if status.success() {
+            return Ok(());
+        }
+
+        let mut reason = match status.code() {
+            Some(code) => format!("exited with code {}", code),
+            None => String::from("was terminated"),
+        };
+
+        // The last line of the error output is usually the most relevant.
+        let stderr = receiver
+            .recv_timeout(STDERR_GRACE_PERIOD)
+            .unwrap_or_default();
+        if let Some(line) = stderr.lines().map(str::trim).rfind(|line| !line.is_empty()) {
+            reason.push_str(": ");
+            reason.push_str(line);
+        }
+
+        Err(reason)
+    }
+}
+
+/// The log of the items for which the hooks have run, so that they don't run for an item again.
+pub struct HookLog {
+    /// The items for which the hooks have run, as `feed\tid`
+    ran: HashSet<String>,
+    path: PathBuf,
+}
+
+impl HookLog {
+    /// Read the hook log stored next to the subscriptions file `subscriptions_file`. Only the last
+    /// [`RUNS_PER_FEED`] items of every feed are kept.
+    pub fn next_to(subscriptions_file: &Path) -> Result<Self> {
+        let path = subscriptions_file.with_file_name(HOOK_LOG_FILE);
+        let ran = read_log(&path, feed_of, RUNS_PER_FEED)?;
+
+        Ok(Self {
+            ran: ran.into_iter().collect(),
+            path,
+        })
+    }
+
+    /// Check whether the hooks have run for `item` of `feed`.
+    fn contains(&self, feed: &Feed, item: &FeedItem) -> bool {
+        self.ran.contains(&log_entry(feed, item))
+    }
+
+    /// Record that the hooks have run for `items` of `feed`.
+    fn record(&mut self, feed: &Feed, items: &[&FeedItem]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+
+        let entries: Vec<_> = items.iter().map(|item| log_entry(feed, item)).collect();
+        append_lines(&self.path, &entries)?;
+        self.ran.extend(entries);
+
+        Ok(())
+    }
+}
+
+/// Get the entry in the hook log for `item` of `feed`. Ids can contain anything, so both parts are
+/// escaped to keep the entry on a single line.
+fn log_entry(feed: &Feed, item: &FeedItem) -> String {
+    format!("{}\t{}", escape(feed.name()), escape(&item.id))
+}
+
+/// Get the part of an entry in the hook log which names the feed.
+fn feed_of(entry: &str) -> &str {
+    entry.split_once('\t').map_or(entry, |(feed, _)| feed)
+}
+
+/// Get the environment variables which are passed to hooks for `item`. The summary and content
+/// are left out, as they can be too large for the environment, they are only passed as JSON.
+fn environment(item: &ExportedItem) -> Vec<(&'static str, String)> {
+    let mut variables = vec![
+        ("GOBBLER_FEED", item.feed.to_string()),
+        ("GOBBLER_TITLE", item.title.to_string()),
+        ("GOBBLER_URL", item.url.clone()),
+        ("GOBBLER_ID", item.id.to_string()),
+        ("GOBBLER_TIMESTAMP", item.timestamp.clone()),
+        (
+            "GOBBLER_AUTHOR",
+            item.author.unwrap_or_default().to_string(),
+        ),
+        ("GOBBLER_CATEGORIES", item.categories.join(",")),
+    ];
+
+    if let Some(enclosure) = item.enclosures.first() {
+        variables.push(("GOBBLER_ENCLOSURE_URL", enclosure.url.to_string()));
+    }
+
+    variables
+}
+
+/// Create a [`Command`] which runs `command` through the shell.
+#[cfg(windows)]
+pub(crate) fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    // `cmd` doesn't follow the usual rules for quoting arguments, so pass the command as is.
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").raw_arg(command);
+    shell
+}
+
+/// Create a [`Command`] which runs `command` through the shell.
+#[cfg(not(windows))]
+pub(crate) fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
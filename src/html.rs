@@ -0,0 +1,190 @@
+//! Functions for converting the HTML found in feed items to plain text which can be shown in a
+//! terminal.
+
+/// Tags which are put on their own line when converted to plain text.
+const LINE_TAGS: &[&str] = &["br", "dd", "div", "dt", "figcaption", "li", "tr"];
+
+/// Tags which are separated from the surrounding text by an empty line when converted to plain
+/// text.
+const PARAGRAPH_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "blockquote",
+    "dl",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+/// Tags whose contents are never shown.
+const HIDDEN_TAGS: &[&str] = &["head", "script", "style", "template"];
+
+/// Convert `html` to plain text. Tags are removed, entities are decoded and whitespace is
+/// collapsed, while block level elements (such as paragraphs and list items) are put on their own
+/// lines.
+pub fn to_plain_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut hidden_until: Option<String> = None;
+    let mut rest = html;
+
+    while let Some(start) = rest.find(['<', '&']) {
+        if hidden_until.is_none() {
+            text.push_str(&rest[..start]);
+        }
+        rest = &rest[start..];
+
+        if rest.starts_with('&') {
+            let (decoded, length) = decode_entity(rest);
+            if hidden_until.is_none() {
+                text.push_str(&decoded);
+            }
+            rest = &rest[length..];
+            continue;
+        }
+
+        // Skip comments entirely, they may contain `>` characters.
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map(|end| &comment[end + 3..])
+                .unwrap_or("");
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        if let Some(hidden) = &hidden_until {
+            if closing && &name == hidden {
+                hidden_until = None;
+            }
+            continue;
+        }
+
+        if !closing && HIDDEN_TAGS.contains(&name.as_str()) && !tag.ends_with('/') {
+            hidden_until = Some(name);
+        } else if PARAGRAPH_TAGS.contains(&name.as_str()) {
+            end_line(&mut text, 2);
+        } else if LINE_TAGS.contains(&name.as_str()) {
+            end_line(&mut text, 1);
+            if name == "li" && !closing {
+                text.push_str("- ");
+            }
+        }
+    }
+
+    if hidden_until.is_none() {
+        text.push_str(rest);
+    }
+
+    collapse_whitespace(&text)
+}
+
+/// End the current line of `text`, making sure it ends with (at least) `count` line breaks.
+fn end_line(text: &mut String, count: usize) {
+    let trailing = text
+        .chars()
+        .rev()
+        .take_while(|c| c.is_whitespace())
+        .filter(|c| *c == '\n')
+        .count();
+
+    for _ in trailing..count {
+        text.push('\n');
+    }
+}
+
+/// Collapse runs of whitespace within lines into a single space, and runs of empty lines into a
+/// single empty line.
+fn collapse_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && lines.last().map(|last| last.is_empty()).unwrap_or(true) {
+            continue;
+        }
+
+        lines.push(line);
+    }
+
+    while lines.last().map(|last| last.is_empty()).unwrap_or(false) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+/// Decode the entity at the start of `text`, returning the decoded text and the length of the
+/// entity. Unknown entities are left as is.
+fn decode_entity(text: &str) -> (String, usize) {
+    let unknown = (String::from("&"), 1);
+
+    let end = match text.find(';') {
+        // Entities are short, so a far away `;` belongs to something else.
+        Some(end) if end <= 10 => end,
+        _ => return unknown,
+    };
+
+    let entity = &text[1..end];
+    let decoded = if let Some(number) = entity.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => number.parse().ok(),
+        };
+
+        match code.and_then(char::from_u32) {
+            Some(c) => c,
+            None => return unknown,
+        }
+    } else {
+        match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            "nbsp" => ' ',
+            "hellip" => '…',
+            "mdash" => '—',
+            "ndash" => '–',
+            "lsquo" => '‘',
+            "rsquo" => '’',
+            "ldquo" => '“',
+            "rdquo" => '”',
+            "laquo" => '«',
+            "raquo" => '»',
+            "copy" => '©',
+            "reg" => '®',
+            "trade" => '™',
+            "middot" => '·',
+            "bull" => '•',
+            _ => return unknown,
+        }
+    };
+
+    (decoded.to_string(), end + 1)
+}
@@ -0,0 +1,238 @@
+//! Support for feeds in the JSON Feed format (versions 1 and 1.1), see https://jsonfeed.org.
+
+use crate::{
+    error::*,
+    feed::{Enclosure, FeedItem},
+    html::to_plain_text,
+};
+use chrono::DateTime;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// The prefix of the `version` of every JSON feed.
+const VERSION_PREFIX: &str = "https://jsonfeed.org/version/";
+
+/// Check whether a feed is a JSON feed, based on the `content_type` it was served with or, if
+/// the server didn't send a useful content type, on its `body`.
+pub(crate) fn is_json_feed(content_type: &str, body: &str) -> bool {
+    content_type.contains("json") || body.trim_start().starts_with('{')
+}
+
+/// Parse the items of the JSON feed `body`. Items without a date are skipped, as it is impossible
+/// to tell whether they are new.
+pub(crate) fn parse_items(body: &str) -> Result<Vec<FeedItem>> {
+    let feed: JsonFeed = serde_json::from_str(body)?;
+    if !feed.version.starts_with(VERSION_PREFIX) {
+        return Err(Error::UnsupportedJsonFeedVersion(feed.version));
+    }
+
+    // Version 1 only supports a single author per feed and per item.
+    let feed_author = feed.authors.into_iter().chain(feed.author).next();
+
+    Ok(feed
+        .items
+        .into_iter()
+        .filter_map(|item| item.into_feed_item(feed_author.as_ref()))
+        .collect())
+}
+
+/// A JSON feed, only containing the fields used by `gobbler`.
+#[derive(Deserialize)]
+struct JsonFeed {
+    version: String,
+    #[serde(default)]
+    items: Vec<JsonFeedItem>,
+    #[serde(default)]
+    authors: Vec<JsonFeedAuthor>,
+    author: Option<JsonFeedAuthor>,
+}
+
+/// An item in a [`JsonFeed`].
+#[derive(Deserialize)]
+struct JsonFeedItem {
+    id: serde_json::Value,
+    url: Option<String>,
+    external_url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+    #[serde(default)]
+    authors: Vec<JsonFeedAuthor>,
+    author: Option<JsonFeedAuthor>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    attachments: Vec<JsonFeedAttachment>,
+}
+
+/// The author of a [`JsonFeed`] or [`JsonFeedItem`].
+#[derive(Clone, Deserialize)]
+struct JsonFeedAuthor {
+    name: Option<String>,
+}
+
+/// A file attached to a [`JsonFeedItem`].
+#[derive(Deserialize)]
+struct JsonFeedAttachment {
+    url: String,
+    mime_type: Option<String>,
+    size_in_bytes: Option<u64>,
+    duration_in_seconds: Option<f64>,
+}
+
+impl JsonFeedItem {
+    /// Convert the item to a [`FeedItem`], falling back to `feed_author` if the item doesn't have
+    /// an author of its own.
+    fn into_feed_item(self, feed_author: Option<&JsonFeedAuthor>) -> Option<FeedItem> {
+        let published = self
+            .date_published
+            .as_deref()
+            .and_then(|published| DateTime::parse_from_rfc3339(published).ok());
+        let timestamp = self.date_modified.or(self.date_published)?;
+        let timestamp = DateTime::parse_from_rfc3339(&timestamp).ok()?;
+
+        let content = match (self.content_text, self.content_html) {
+            (Some(text), _) => Some(text),
+            (None, Some(html)) => Some(to_plain_text(&html)),
+            (None, None) => None,
+        }
+        .filter(|content| !content.is_empty());
+
+        // The title is optional, as not every item is a blog post (think microblogs).
+        let title = self
+            .title
+            .filter(|title| !title.is_empty())
+            .or_else(|| self.summary.clone())
+            .or_else(|| {
+                content
+                    .as_ref()
+                    .and_then(|content| content.lines().next().map(String::from))
+            })
+            .unwrap_or_default();
+
+        let id = match self.id {
+            serde_json::Value::String(id) => id,
+            id => id.to_string(),
+        };
+
+        let author = self
+            .authors
+            .into_iter()
+            .chain(self.author)
+            .next()
+            .or_else(|| feed_author.cloned())
+            .and_then(|author| author.name)
+            .filter(|name| !name.is_empty());
+
+        let enclosures = self
+            .attachments
+            .into_iter()
+            .map(|attachment| Enclosure {
+                url: attachment.url,
+                mime_type: attachment.mime_type,
+                length: attachment.size_in_bytes.filter(|length| *length > 0),
+                duration: attachment
+                    .duration_in_seconds
+                    .filter(|seconds| *seconds > 0.0)
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
+            })
+            .collect();
+
+        Some(FeedItem {
+            title,
+            id: self.url.or(self.external_url).unwrap_or(id),
+            timestamp,
+            published,
+            summary: self.summary.map(|summary| to_plain_text(&summary)),
+            author,
+            categories: self.tags,
+            content,
+            enclosures,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_items() {
+        let body = r#"{
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "Blog",
+            "authors": [{"name": "Ann"}],
+            "items": [
+                {
+                    "id": "1",
+                    "url": "https://example.com/posts/1",
+                    "title": "Hello",
+                    "content_html": "<p>Hi &amp; bye</p>",
+                    "date_published": "2024-05-01T10:00:00Z",
+                    "tags": ["rust"],
+                    "attachments": [{
+                        "url": "https://example.com/episode.mp3",
+                        "mime_type": "audio/mpeg",
+                        "size_in_bytes": 1000000,
+                        "duration_in_seconds": 61
+                    }]
+                },
+                {
+                    "id": 2,
+                    "external_url": "https://example.org/",
+                    "content_text": "A micro post\nwithout a title",
+                    "date_modified": "2024-05-02T10:00:00+02:00",
+                    "author": {"name": "Bob"}
+                },
+                {"id": "3", "title": "Without a date"}
+            ]
+        }"#;
+
+        let items = parse_items(body).unwrap();
+        assert_eq!(items.len(), 2);
+
+        assert_eq!(items[0].title, "Hello");
+        assert_eq!(items[0].id, "https://example.com/posts/1");
+        assert_eq!(items[0].content.as_deref(), Some("Hi & bye"));
+        assert_eq!(items[0].author.as_deref(), Some("Ann"));
+        assert_eq!(items[0].categories, ["rust"]);
+        assert_eq!(
+            items[0].enclosures[0].to_string(),
+            "https://example.com/episode.mp3 (audio/mpeg, 1.0 MB, 0:01:01)"
+        );
+
+        assert_eq!(items[1].title, "A micro post");
+        assert_eq!(items[1].id, "https://example.org/");
+        assert_eq!(items[1].author.as_deref(), Some("Bob"));
+    }
+
+    #[test]
+    fn ignores_durations_which_are_too_large() {
+        let body = r#"{
+            "version": "https://jsonfeed.org/version/1",
+            "title": "Podcast",
+            "items": [{
+                "id": "1",
+                "url": "https://example.com/episodes/1",
+                "title": "Episode",
+                "date_published": "2024-05-01T10:00:00Z",
+                "attachments": [{"url": "https://example.com/1.mp3", "duration_in_seconds": 1e30}]
+            }]
+        }"#;
+
+        let items = parse_items(body).unwrap();
+        assert_eq!(items[0].enclosures[0].duration, None);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert!(matches!(
+            parse_items(r#"{"version": "1.0", "items": []}"#),
+            Err(Error::UnsupportedJsonFeedVersion(_))
+        ));
+        assert!(parse_items("{").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+#![doc = include_str!("../README.md")]
+#![warn(missing_docs)]
+
+#[cfg(not(unix))]
+mod acl;
+pub mod archive;
+pub mod auth;
+pub mod bookmarks;
+pub mod browser;
+pub mod dates;
+pub mod dedupe;
+pub mod download;
+pub mod error;
+pub mod feed;
+pub mod filter;
+pub mod hook;
+pub mod html;
+pub mod http;
+mod json_feed;
+mod lines;
+pub mod listing;
+pub mod marks;
+pub mod opml;
+pub mod publish;
+pub mod reg;
+pub mod scrape;
+pub mod serve;
+pub mod source;
+pub mod stats;
+pub mod terminal;
+pub mod tui;
+pub mod watch;
+pub mod webhook;
+pub mod window;
+mod xml;
+
+pub use crate::{
+    error::{Error, Result},
+    feed::{Database, Enclosure, Feed, FeedItem, Subscription},
+    filter::{Filter, ItemFilter},
+    http::{HttpClient, HttpOptions},
+    window::TimeWindow,
+};
@@ -0,0 +1,175 @@
+//! Reading and writing the line based files in which `gobbler` stores its state, such as the
+//! webhooks and the marked items, next to the subscriptions file.
+
+use crate::error::*;
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+/// Read the lines of `path`, which is treated as empty if it does not exist yet.
+pub(crate) fn read_lines(path: &Path) -> Result<Vec<String>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut lines = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Read the log at `path`, to which entries are appended as they happen, keeping only the last
+/// `limit` entries of every group of entries, as given by `group_of`. The log is rewritten when
+/// entries are dropped.
+pub(crate) fn read_log(
+    path: &Path,
+    group_of: impl Fn(&str) -> &str,
+    limit: usize,
+) -> Result<Vec<String>> {
+    let entries = read_lines(path)?;
+
+    // Entries are appended to the log, so the oldest entries come first.
+    let mut counts = HashMap::new();
+    let mut kept: Vec<&String> = entries
+        .iter()
+        .rev()
+        .filter(|entry| {
+            let count = counts.entry(group_of(entry)).or_insert(0);
+            *count += 1;
+            *count <= limit
+        })
+        .collect();
+    kept.reverse();
+    if kept.len() < entries.len() {
+        write_lines(path, kept.iter().map(|entry| entry.to_string()))?;
+    }
+
+    Ok(kept.into_iter().cloned().collect())
+}
+
+/// Replace the contents of the file at `path` with `lines`.
+pub(crate) fn write_lines(path: &Path, lines: impl IntoIterator<Item = String>) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    for line in lines {
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// Append `lines` to the file at `path`.
+pub(crate) fn append_lines(path: &Path, lines: &[String]) -> Result<()> {
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    for line in lines {
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// Escape the backslashes, tabs and line breaks in `field`, so that it can be stored as a tab
+/// separated field of a line, see [`unescape`].
+pub(crate) fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Undo [`escape`]. Backslashes which don't start an escape sequence are kept as they are, so
+/// fields which were stored before they were escaped are read as they were.
+pub(crate) fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('\\', Some('\\')) => '\\',
+            ('\\', Some('t')) => '\t',
+            ('\\', Some('r')) => '\r',
+            ('\\', Some('n')) => '\n',
+            (c, _) => {
+                unescaped.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        unescaped.push(escaped);
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn round_trips_escaped_fields() {
+        for field in [
+            "",
+            "plain",
+            "a\tb",
+            "line\r\nbreak",
+            "back\\slash",
+            "\\t",
+            "\\\\n\t\\",
+        ] {
+            let escaped = escape(field);
+            assert!(!escaped.contains(['\t', '\r', '\n']), "{escaped}");
+            assert_eq!(unescape(&escaped), field);
+        }
+    }
+
+    #[test]
+    fn keeps_unknown_escape_sequences() {
+        assert_eq!(unescape("C:\\Users\\ann"), "C:\\Users\\ann");
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+    }
+
+    fn group_of(entry: &str) -> &str {
+        entry.split('\t').next().unwrap_or_default()
+    }
+
+    #[test]
+    fn prunes_logs() {
+        let path = env::temp_dir().join(format!("gobbler-lines-test-{}.log", process::id()));
+        let mut entries: Vec<_> = (0..5).map(|i| format!("a\t{i}")).collect();
+        entries.push(String::from("b\t0"));
+        write_lines(&path, entries).unwrap();
+
+        let kept = read_log(&path, group_of, 3).unwrap();
+        assert_eq!(kept, ["a\t2", "a\t3", "a\t4", "b\t0"]);
+        assert_eq!(read_lines(&path).unwrap(), kept);
+
+        append_lines(&path, &[String::from("b\t1")]).unwrap();
+        assert_eq!(read_log(&path, group_of, 3).unwrap().len(), 5);
+
+        fs::remove_file(&path).unwrap();
+        assert!(read_log(&path, group_of, 3).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+//! Numbering the posts which are shown, so that they can be referred to by their number
+//! afterwards, such as by `gobbler open 3`.
+
+use crate::{
+    error::*,
+    feed::{Feed, FeedItem},
+    lines::{escape, read_lines, unescape, write_lines},
+};
+use std::path::{Path, PathBuf};
+
+/// The file used to store the most recent listing, which is stored next to the subscriptions file.
+pub const LISTING_FILE: &str = "listing.db";
+
+/// A post in a [`Listing`].
+pub struct ListedItem {
+    /// The name of the feed the post belongs to
+    pub feed: String,
+
+    /// The id of the post
+    pub id: String,
+
+    /// The url of the post
+    pub url: String,
+}
+
+/// The numbered posts which were shown most recently.
+pub struct Listing {
+    /// The path to the listing file
+    path: PathBuf,
+
+    /// The posts, where the post numbered `n` is stored at index `n - 1`
+    items: Vec<ListedItem>,
+}
+
+impl Listing {
+    /// Load the most recent listing from the listing file next to `subscriptions_file`.
+    pub fn next_to(subscriptions_file: &Path) -> Result<Self> {
+        let path = subscriptions_file.with_file_name(LISTING_FILE);
+
+        let items = read_lines(&path)?
+            .into_iter()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some(ListedItem {
+                    feed: unescape(fields.next()?),
+                    id: unescape(fields.next()?),
+                    url: unescape(fields.next()?),
+                })
+            })
+            .collect();
+
+        Ok(Self { path, items })
+    }
+
+    /// Remove all posts from the listing, so that numbering starts over.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Add `item`, a post of `feed`, to the listing, returning its number.
+    pub fn push(&mut self, feed: &Feed, item: &FeedItem) -> Result<usize> {
+        self.items.push(ListedItem {
+            feed: feed.name().to_string(),
+            id: item.id.clone(),
+            url: feed.post_url(item)?.to_string(),
+        });
+
+        Ok(self.items.len())
+    }
+
+    /// Get the post with the given number.
+    pub fn get(&self, number: usize) -> Option<&ListedItem> {
+        number
+            .checked_sub(1)
+            .and_then(|index| self.items.get(index))
+    }
+
+    /// Get the number of posts in the listing.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Check whether the listing contains no posts.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Write the listing to the listing file, replacing its current contents. Every post is
+    /// stored on a single line, as its number is the number of the line.
+    pub fn save(&self) -> Result<()> {
+        write_lines(
+            &self.path,
+            self.items.iter().map(|item| {
+                format!(
+                    "{}\t{}\t{}",
+                    escape(&item.feed),
+                    escape(&item.id),
+                    escape(&item.url)
+                )
+            }),
+        )
+    }
+}
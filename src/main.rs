@@ -1,19 +1,50 @@
-#![doc = include_str!("../README.md")]
+//! The `gobbler` command line interface, built on top of the `gobbler` library.
 
-mod error;
-mod feed;
-mod reg;
-
-use crate::{
+use chrono::{DateTime, Local, Utc};
+use clap::{Parser, Subcommand};
+use gobbler::{
+    archive::{Archive, FetchHistory},
+    auth::{self, credential_keys, store_credential, Auth, Credentials},
+    bookmarks::{self, ExportFormat},
+    browser::Opener,
+    dates::{DateFormatter, Zone, DEFAULT_DATE_FORMAT, DEFAULT_GROUPED_DATE_FORMAT},
+    dedupe::dedupe,
+    download,
     error::*,
-    feed::{Database, DB_FILE},
+    feed::{
+        fetch_items, parse_tags, Database, DisplayOptions, Feed, FeedItem, Subscription, DB_FILE,
+    },
+    filter::{Filter, ItemField, ItemFilter},
+    hook::{HookLog, HookRunner},
+    http::{HttpClient, HttpOptions},
+    listing::Listing,
+    marks::{Marks, StarredItem},
+    opml,
+    publish::{combine, to_feed, FeedInfo, PublishFormat},
     reg::*,
+    scrape::{self, ScrapeRules},
+    serve::Server,
+    stats::FeedStats,
+    terminal::{terminal_width, HyperlinkMode, UrlStyle},
+    tui::Tui,
+    watch::{format_interval, parse_interval, WatchEvent, Watcher},
+    webhook::{parse_names, DeliveryLog, PayloadFormat, Webhook, Webhooks},
+    window::{PointInTime, TimeWindow},
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::{
+    cmp::Reverse,
+    io::Write,
+    path::{Path, PathBuf},
+    process::exit,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
-use chrono::{Duration, Utc, DateTime, Local};
-use clap::{Parser, Subcommand};
-use std::{io::Write, ops::Sub, path::PathBuf, process::exit, str::FromStr};
 use termcolor::{ColorChoice, StandardStream};
-use windows::{core::HSTRING, Foundation::Uri, Web::Syndication::SyndicationClient};
+use url::Url;
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -32,11 +63,16 @@ struct Options {
     #[clap(long = "export", short = 'e')]
     export: bool,
 
-    /// Imports the subscriptions listed in FILE
+    /// Exports the current subscriptions to FILE as OPML, storing tags as categories
+    #[clap(long = "export-opml", value_name = "FILE")]
+    export_opml: Option<String>,
+
+    /// Imports the subscriptions listed in FILE. OPML files (.opml or .xml) are merged into the
+    /// current subscriptions, with their categories mapped onto tags
     #[clap(long = "import", short = 'i', value_name = "FILE")]
     import: Option<String>,
 
-    /// List RSS feed subscriptions
+    /// List RSS feed subscriptions, grouped by tag
     #[clap(long = "list", short = 'l')]
     list: bool,
 
@@ -56,6 +92,19 @@ struct Options {
     #[clap(long = "new-only", short = 'N', conflicts_with_all = &["weeks", "run_days"])]
     new_only: bool,
 
+    /// Show posts from after WHEN, which is a date (2024-05-01), a duration (36h, 3d, 2w) or a
+    /// keyword (today, yesterday, last-monday)
+    #[clap(
+        long = "since",
+        value_name = "WHEN",
+        conflicts_with_all = &["weeks", "new_only"]
+    )]
+    since: Option<String>,
+
+    /// Show posts from before WHEN, see --since. Dates and keywords include that entire day
+    #[clap(long = "until", value_name = "WHEN")]
+    until: Option<String>,
+
     /// Show new feed items every NUM days
     #[clap(
         long = "run-days",
@@ -70,6 +119,37 @@ struct Options {
     #[clap(long = "filter-name", short = 'n', value_name = "NAME")]
     filter_by_name: Option<String>,
 
+    /// Only show feed items from feeds tagged with TAG (can be repeated)
+    #[clap(long = "tag", short = 't', value_name = "TAG", value_delimiter = ',')]
+    tags: Vec<String>,
+
+    /// Hide feed items from feeds tagged with TAG (can be repeated)
+    #[clap(
+        long = "exclude-tag",
+        short = 'x',
+        value_name = "TAG",
+        value_delimiter = ','
+    )]
+    exclude_tags: Vec<String>,
+
+    /// Only show feed items matching REGEX (case insensitive)
+    #[clap(long = "match", short = 'm', value_name = "REGEX")]
+    match_items: Option<String>,
+
+    /// Hide feed items matching REGEX (case insensitive)
+    #[clap(long = "exclude", value_name = "REGEX")]
+    exclude_items: Option<String>,
+
+    /// The fields of feed items which are matched by --match, --exclude and the rules of a feed
+    #[clap(
+        long = "match-in",
+        value_name = "FIELDS",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "title"
+    )]
+    match_in: Vec<ItemField>,
+
     /// Show at most LIMIT posts per feed
     #[clap(
         long = "limit",
@@ -83,6 +163,119 @@ struct Options {
     #[clap(long = "count-only", short = 'c')]
     count_only: bool,
 
+    /// Show an excerpt of the summary of each post
+    #[clap(long = "summary", short = 'S', conflicts_with = "count_only")]
+    summary: bool,
+
+    /// Show timestamps in ZONE, such as Europe/Amsterdam or America/New_York, instead of the local
+    /// time zone
+    #[clap(
+        long = "timezone",
+        value_name = "ZONE",
+        env = "GOBBLER_TIMEZONE",
+        default_value = "local"
+    )]
+    timezone: String,
+
+    /// Show timestamps using FORMAT, such as "%Y-%m-%d %H:%M", see
+    /// https://docs.rs/chrono/latest/chrono/format/strftime
+    #[clap(
+        long = "date-format",
+        value_name = "FORMAT",
+        env = "GOBBLER_DATE_FORMAT"
+    )]
+    date_format: Option<String>,
+
+    /// Show timestamps relative to now, such as "3h ago" or "yesterday", instead of using
+    /// --date-format
+    #[clap(long = "relative-dates")]
+    relative_dates: bool,
+
+    /// Show the posts of every feed under a heading for every day
+    #[clap(long = "group-by-day")]
+    group_by_day: bool,
+
+    /// Make the titles of posts clickable links, when the terminal supports them (auto), or
+    /// regardless (always)
+    #[clap(long = "hyperlinks", value_enum, default_value = "auto")]
+    hyperlinks: HyperlinkMode,
+
+    /// Show the full urls of posts, shorten them, or hide them when the titles are clickable
+    #[clap(long = "urls", value_enum, default_value = "full")]
+    urls: UrlStyle,
+
+    /// Truncate titles so that posts fit on lines of COLS characters, which defaults to the width
+    /// of the terminal. Pass 0 to never truncate titles
+    #[clap(long = "width", value_name = "COLS")]
+    width: Option<usize>,
+
+    /// Hide posts which were first published before the posts which are shown, and only show up
+    /// because they were updated since
+    #[clap(long = "hide-updates")]
+    hide_updates: bool,
+
+    /// Show posts which appear in multiple feeds every time, instead of only once with the other
+    /// feeds listed after "also in"
+    #[clap(long = "no-dedupe")]
+    no_dedupe: bool,
+
+    /// Also take posts with similar titles, published within a week of each other, as the same
+    /// post when removing posts which appear in multiple feeds
+    #[clap(long = "fuzzy-titles", conflicts_with = "no_dedupe")]
+    fuzzy_titles: bool,
+
+    /// Show a number in front of every post, which can be passed to `open` to open that post
+    #[clap(long = "number", conflicts_with = "count_only")]
+    number: bool,
+
+    /// Open posts using CMD instead of the web browser. Quote the program in CMD if its path
+    /// contains spaces. In CMD, {} is replaced by the url of the post, which is passed as the last
+    /// argument otherwise
+    #[clap(long = "opener", value_name = "CMD", env = "GOBBLER_OPENER")]
+    opener: Option<String>,
+
+    /// Don't store fetched posts in the archive which is searched by `search`
+    #[clap(long = "no-archive")]
+    no_archive: bool,
+
+    /// Send all requests through the proxy at URL, such as http://proxy.example.com:8080 or
+    /// socks5://localhost:1080, instead of the proxy set by HTTPS_PROXY, HTTP_PROXY or ALL_PROXY
+    #[clap(long = "proxy", value_name = "URL", env = "GOBBLER_PROXY")]
+    proxy: Option<String>,
+
+    /// Comma separated list of hosts which are reached directly rather than through the proxy,
+    /// instead of the hosts set by NO_PROXY
+    #[clap(long = "no-proxy", value_name = "HOSTS")]
+    no_proxy: Option<String>,
+
+    /// Trust the certificates in FILE (in PEM format) in addition to the usual root certificates,
+    /// such as the certificate of a proxy which inspects TLS traffic
+    #[clap(long = "ca-bundle", value_name = "FILE", env = "GOBBLER_CA_BUNDLE")]
+    ca_bundles: Vec<PathBuf>,
+
+    /// Don't verify the certificate of HOST at all. Anyone between you and HOST can read and
+    /// change the traffic, so only use this for hosts on a network you trust
+    #[clap(
+        long = "insecure-host",
+        value_name = "HOST",
+        env = "GOBBLER_INSECURE_HOSTS",
+        value_delimiter = ','
+    )]
+    insecure_hosts: Vec<String>,
+
+    /// Run CMD for every new post, when watching feeds or when using --new-only. The post is
+    /// passed to CMD as environment variables and as JSON on standard input
+    #[clap(long = "on-new-item", value_name = "CMD")]
+    on_new_item: Option<String>,
+
+    /// Run at most NUM hooks at the same time
+    #[clap(long = "hook-jobs", value_name = "NUM", default_value = "4")]
+    hook_jobs: usize,
+
+    /// Stop hooks which take longer than INTERVAL, such as 30s or 2m
+    #[clap(long = "hook-timeout", value_name = "INTERVAL", default_value = "30s")]
+    hook_timeout: String,
+
     #[clap(subcommand)]
     command: Option<Commands>,
 }
@@ -98,6 +291,194 @@ enum Commands {
         /// The url of the blog's RSS feed
         #[clap(value_name = "URL")]
         url: String,
+
+        /// Comma separated list of tags for the blog
+        #[clap(long = "tags", value_name = "TAGS")]
+        tags: Option<String>,
+
+        /// Authenticate using HTTP basic authentication, with the password referenced by SECRET
+        /// (env:VARIABLE, file:KEY or cmd:COMMAND)
+        #[clap(long = "basic", value_name = "USER:SECRET")]
+        basic: Option<String>,
+
+        /// Authenticate using the bearer token referenced by SECRET
+        #[clap(long = "bearer", value_name = "SECRET", conflicts_with = "basic")]
+        bearer: Option<String>,
+
+        /// Send a header whose value is referenced by SECRET, such as 'X-Api-Key: env:API_KEY'.
+        /// Can be given multiple times
+        #[clap(long = "header", value_name = "NAME: SECRET")]
+        headers: Vec<String>,
+
+        /// Scrape the posts from the page at URL instead, for sites without a feed, each post
+        /// being an element matching SELECTOR (a CSS selector such as 'article')
+        #[clap(long = "scrape-item", value_name = "SELECTOR")]
+        scrape_item: Option<String>,
+
+        /// The CSS selector matching the title of a scraped post, within the post
+        #[clap(long = "scrape-title", value_name = "SELECTOR")]
+        scrape_title: Option<String>,
+
+        /// The CSS selector matching the link to a scraped post, within the post
+        #[clap(long = "scrape-link", value_name = "SELECTOR")]
+        scrape_link: Option<String>,
+
+        /// The CSS selector matching the date of a scraped post, within the post
+        #[clap(long = "scrape-date", value_name = "SELECTOR")]
+        scrape_date: Option<String>,
+
+        /// The format of the date of a scraped post, such as '%d %B %Y'. By default, RFC 3339 and
+        /// RFC 2822 timestamps and YYYY-MM-DD dates are understood
+        #[clap(long = "scrape-date-format", value_name = "FORMAT")]
+        scrape_date_format: Option<String>,
+    },
+
+    /// Set the credentials a RSS feed subscription is fetched with, replacing any existing
+    /// credentials. Secrets are referenced as env:VARIABLE, file:KEY (see `credential`) or
+    /// cmd:COMMAND, and are never stored in the subscriptions file. Passing no credentials removes
+    /// them
+    Auth {
+        /// The name of the blog
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// Authenticate using HTTP basic authentication, with the password referenced by SECRET
+        #[clap(long = "basic", value_name = "USER:SECRET")]
+        basic: Option<String>,
+
+        /// Authenticate using the bearer token referenced by SECRET
+        #[clap(long = "bearer", value_name = "SECRET", conflicts_with = "basic")]
+        bearer: Option<String>,
+
+        /// Send a header whose value is referenced by SECRET, such as 'X-Api-Key: env:API_KEY'.
+        /// Can be given multiple times
+        #[clap(long = "header", value_name = "NAME: SECRET")]
+        headers: Vec<String>,
+    },
+
+    /// Store a secret in the credentials file, which can only be read by you, so that it can be
+    /// referenced as file:KEY. The secret is read from standard input. Omitting the key lists the
+    /// keys of the stored secrets
+    Credential {
+        /// The key to store the secret under
+        #[clap(value_name = "KEY")]
+        key: Option<String>,
+
+        /// Remove the secret stored under KEY instead
+        #[clap(long = "remove", requires = "key")]
+        remove: bool,
+    },
+
+    /// Download the files enclosed with the latest items of a feed, such as podcast episodes
+    Download {
+        /// The name of the blog
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// Download the enclosures of the latest NUM items which have enclosures
+        #[clap(long = "latest", value_name = "NUM", default_value = "1")]
+        latest: usize,
+
+        /// The directory to download the enclosures to
+        #[clap(long = "dir", short = 'd', value_name = "PATH", default_value = ".")]
+        dir: PathBuf,
+    },
+
+    /// Set the command which is run for every new item of a RSS feed subscription, see
+    /// --on-new-item. Omitting the command removes it
+    Hook {
+        /// The name of the blog
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// The command to run
+        #[clap(value_name = "CMD")]
+        command: Option<String>,
+    },
+
+    /// Set the interval at which a RSS feed subscription is polled by `watch`, overriding the
+    /// interval passed to `watch` and the interval the feed asks for. Omitting the interval removes
+    /// it
+    Interval {
+        /// The name of the blog
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// The interval, such as 30s, 15m, 2h or 1d
+        #[clap(value_name = "INTERVAL")]
+        interval: Option<String>,
+    },
+
+    /// Set the rules which items of a RSS feed subscription have to match in order to be shown,
+    /// replacing any existing rules. Passing neither rule removes all rules
+    Rules {
+        /// The name of the blog
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// Only show items matching REGEX (case insensitive)
+        #[clap(long = "include", value_name = "REGEX")]
+        include: Option<String>,
+
+        /// Hide items matching REGEX (case insensitive)
+        #[clap(long = "exclude", value_name = "REGEX")]
+        exclude: Option<String>,
+    },
+
+    /// Set the tags of a RSS feed subscription, replacing any existing tags
+    Tag {
+        /// The name of the blog
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// Comma separated list of tags, pass an empty list to remove all tags
+        #[clap(value_name = "TAGS")]
+        tags: String,
+    },
+
+    /// Open a post in the web browser: the Nth post of a feed, a post numbered by --number, or the
+    /// post with a given id
+    Open {
+        /// The name of the blog, or the number of a post as shown by --number
+        #[clap(value_name = "NAME|NUMBER", required_unless_present = "id")]
+        target: Option<String>,
+
+        /// Open the Nth post of the blog, as it is listed
+        #[clap(
+            value_name = "N",
+            conflicts_with = "id",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        number: Option<u64>,
+
+        /// Open the post with this id or url, searching the given blog or all blogs
+        #[clap(long = "id", value_name = "ID")]
+        id: Option<String>,
+
+        /// Print the command which would open the post instead of running it
+        #[clap(long = "dry-run")]
+        dry_run: bool,
+    },
+
+    /// Write the posts of the RSS feeds to a single, combined Atom or RSS feed, so that it can be
+    /// published or read elsewhere. Respects the options which select posts, such as --weeks,
+    /// --since, --tag and --limit
+    Publish {
+        /// The file to write the combined feed to
+        #[clap(long = "out", short = 'o', value_name = "FILE")]
+        out: PathBuf,
+
+        /// The format of the combined feed
+        #[clap(long = "format", value_enum, default_value = "atom")]
+        format: PublishFormat,
+
+        /// The title of the combined feed
+        #[clap(long = "title", value_name = "TITLE", default_value = "gobbler")]
+        title: String,
+
+        /// The url the combined feed will be published at
+        #[clap(long = "url", value_name = "URL")]
+        url: Option<String>,
     },
 
     /// Remove a RSS feed subscription
@@ -106,6 +487,193 @@ enum Commands {
         #[clap(value_name = "NAME")]
         name: String,
     },
+
+    /// Show what scraping rules extract from a page, or from a saved HTML file, before adding a
+    /// subscription which uses them
+    ScrapeTest {
+        /// The url of the page, or the path to a saved HTML file
+        #[clap(value_name = "URL|FILE")]
+        source: String,
+
+        /// The CSS selector matching every post on the page, such as 'article'
+        #[clap(long = "scrape-item", value_name = "SELECTOR")]
+        scrape_item: String,
+
+        /// The CSS selector matching the title of a post, within the post
+        #[clap(long = "scrape-title", value_name = "SELECTOR")]
+        scrape_title: String,
+
+        /// The CSS selector matching the link to a post, within the post
+        #[clap(long = "scrape-link", value_name = "SELECTOR")]
+        scrape_link: String,
+
+        /// The CSS selector matching the date of a post, within the post
+        #[clap(long = "scrape-date", value_name = "SELECTOR")]
+        scrape_date: String,
+
+        /// The format of the date of a post, such as '%d %B %Y'
+        #[clap(long = "scrape-date-format", value_name = "FORMAT")]
+        scrape_date_format: Option<String>,
+    },
+
+    /// Search all posts which were ever fetched, including posts which are no longer in their
+    /// feed. Posts match when they contain every word of QUERY in their title, summary, content
+    /// or author. End a word with * to match any word starting with it
+    Search {
+        /// The words to search for
+        #[clap(value_name = "QUERY", required = true, num_args = 1..)]
+        query: Vec<String>,
+
+        /// Only search the posts of the blog named NAME
+        #[clap(long = "feed", value_name = "NAME")]
+        feed: Option<String>,
+
+        /// Only search posts from after WHEN, which is a date (2024-05-01), a duration (36h, 3d,
+        /// 2w) or a keyword (today, yesterday, last-monday)
+        #[clap(long = "since", value_name = "WHEN")]
+        since: Option<String>,
+
+        /// Show at most NUM posts, the best matches first
+        #[clap(long = "results", value_name = "NUM", default_value = "20")]
+        results: usize,
+    },
+
+    /// Show statistics about the RSS feeds, based on the posts and fetches stored in the archive:
+    /// the number of posts over time, how often they post, and how reliably they can be fetched
+    Stats {
+        /// Print the statistics as JSON
+        #[clap(long = "json")]
+        json: bool,
+    },
+
+    /// Star a post, keeping it in the list of starred posts even after it has dropped out of its
+    /// feed
+    Star {
+        /// The id or url of the post, or the number of the post as shown by --number
+        #[clap(value_name = "ID|NUMBER")]
+        target: String,
+
+        /// Only look for the post in the blog named NAME
+        #[clap(long = "feed", value_name = "NAME")]
+        feed: Option<String>,
+    },
+
+    /// List the starred posts, newest first, or export them
+    Starred {
+        /// Export the starred posts in FORMAT instead of listing them
+        #[clap(long = "export", value_name = "FORMAT", value_enum)]
+        export: Option<ExportFormat>,
+
+        /// The file to write the exported posts to, instead of standard output
+        #[clap(long = "out", short = 'o', value_name = "FILE", requires = "export")]
+        out: Option<PathBuf>,
+    },
+
+    /// Serve the posts of the RSS feeds over HTTP, as a combined Atom feed (/feed.atom), as JSON
+    /// (/items.json) and as a web page (/). Add ?tag=TAG to only serve the posts of feeds tagged
+    /// with TAG
+    Serve {
+        /// The port to listen on
+        #[clap(
+            long = "port",
+            short = 'p',
+            value_name = "PORT",
+            default_value = "8080"
+        )]
+        port: u16,
+
+        /// The address to listen on, use 0.0.0.0 to make the server reachable from other machines
+        #[clap(long = "host", value_name = "ADDRESS", default_value = "127.0.0.1")]
+        host: String,
+
+        /// Fetch the feeds again when they were last fetched longer than INTERVAL ago, such as 15m
+        #[clap(long = "cache", value_name = "INTERVAL", default_value = "15m")]
+        cache: String,
+    },
+
+    /// Show the posts of a feed without subscribing to it. Respects the options which select and
+    /// display posts, such as --weeks, --since and --summary
+    Show {
+        /// The url of the feed, a file:// url, exec:COMMAND to show the feed printed by COMMAND,
+        /// or - to read the feed from standard input
+        #[clap(value_name = "SOURCE")]
+        source: String,
+    },
+
+    /// Browse the posts of the RSS feeds in a full-screen interface, in which posts can be opened,
+    /// marked as read and starred. Respects the options which select posts, such as --weeks,
+    /// --since and --tag
+    Tui,
+
+    /// Keep running, polling the RSS feeds and showing new items as they appear, until Ctrl+C is
+    /// pressed
+    Watch {
+        /// Poll every feed every INTERVAL (such as 30s, 15m or 2h), unless the feed asks to be
+        /// polled less often
+        #[clap(long = "interval", value_name = "INTERVAL", default_value = "15m")]
+        interval: String,
+    },
+
+    /// Unstar a starred post
+    Unstar {
+        /// The id or url of the post, or the number of the post as shown by --number
+        #[clap(value_name = "ID|NUMBER")]
+        target: String,
+
+        /// Only look for the post in the blog named NAME
+        #[clap(long = "feed", value_name = "NAME")]
+        feed: Option<String>,
+    },
+
+    /// Manage the webhooks to which new posts are delivered
+    Webhook {
+        #[clap(subcommand)]
+        command: WebhookCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum WebhookCommands {
+    /// Add a webhook to which new posts are delivered, when watching feeds or when using
+    /// --new-only
+    Add {
+        /// The name of the webhook
+        #[clap(value_name = "NAME")]
+        name: String,
+
+        /// The url to post new posts to
+        #[clap(value_name = "URL")]
+        url: String,
+
+        /// The shape of the payload
+        #[clap(long = "format", value_enum, default_value = "generic")]
+        format: PayloadFormat,
+
+        /// Comma separated list of names of the feeds whose posts are delivered
+        #[clap(long = "feeds", value_name = "NAMES")]
+        feeds: Option<String>,
+
+        /// Comma separated list of tags of the feeds whose posts are delivered
+        #[clap(long = "tags", value_name = "TAGS")]
+        tags: Option<String>,
+    },
+
+    /// List the webhooks
+    List,
+
+    /// Remove a webhook
+    Remove {
+        /// The name of the webhook
+        #[clap(value_name = "NAME")]
+        name: String,
+    },
+
+    /// Post a test post to a webhook
+    Test {
+        /// The name of the webhook
+        #[clap(value_name = "NAME")]
+        name: String,
+    },
 }
 
 fn main() {
@@ -129,11 +697,13 @@ fn run() -> Result<()> {
         return Ok(());
     }
 
-    if let Some(import_file) = options.import {
-        println!("Importing subscriptions from {DB_FILE}");
-        Database::import_from(&import_file)?;
-        println!("Import successful");
-        return Ok(());
+    if let Some(import_file) = &options.import {
+        if !opml::is_opml_file(Path::new(import_file)) {
+            println!("Importing subscriptions from {DB_FILE}");
+            Database::import_from(import_file)?;
+            println!("Import successful");
+            return Ok(());
+        }
     }
 
     let mut db = if let Some(subscriptions_file) = options.subscriptions_file {
@@ -141,15 +711,140 @@ fn run() -> Result<()> {
     } else {
         Database::new()?
     };
+    // The posts which are fetched are stored in the archive, but failing to open it shouldn't
+    // get in the way of showing them.
+    if !options.no_archive && fetches_feeds(&options.command) {
+        if let Err(error) = db.open_archive() {
+            eprintln!(
+                "WARNING: Failed to open the archive, the posts which are fetched are not \
+                 archived: {}",
+                error
+            );
+        }
+    }
     let mut stdout = StandardStream::stdout(ColorChoice::Auto);
 
+    if let Some(export_file) = options.export_opml {
+        println!("Exporting subscriptions to {export_file}");
+        opml::write_subscriptions(&db, Path::new(&export_file))?;
+        println!("Export successful");
+        return Ok(());
+    }
+
+    if let Some(import_file) = options.import {
+        println!("Importing subscriptions from {import_file}");
+        for (name, subscription) in opml::read_subscriptions(Path::new(&import_file))? {
+            match db.add(name, subscription) {
+                Ok(_) => {}
+                Err(Error::DuplicateName { name, .. }) => {
+                    eprintln!(
+                        "WARNING: Skipping '{name}', as you are already subscribed to a feed with \
+                         that name"
+                    );
+                }
+                Err(error @ (Error::InvalidFeedName(_) | Error::InvalidRssFeedUrl(_))) => {
+                    eprintln!("WARNING: Skipping an outline: {}", error);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        println!("Import successful");
+        return Ok(());
+    }
+
+    let item_filter = ItemFilter::new(
+        options.match_items.as_deref(),
+        options.exclude_items.as_deref(),
+        options.match_in,
+    )?;
+    let filter = Filter::new(
+        options.filter_by_name,
+        options.tags,
+        options.exclude_tags,
+        item_filter,
+    );
+
+    let hooks = HookRunner::new(
+        options.on_new_item,
+        options.hook_jobs,
+        parse_interval(&options.hook_timeout)?,
+    );
+
+    let client = HttpClient::with_options(&HttpOptions {
+        proxy: options.proxy,
+        no_proxy: options.no_proxy,
+        ca_bundles: options.ca_bundles,
+        insecure_hosts: options.insecure_hosts,
+    })?;
+    let opener = Opener::new(options.opener);
+    let hyperlinks = options.hyperlinks.enabled();
+    let zone: Zone = options.timezone.parse()?;
+    let dates = if options.relative_dates {
+        DateFormatter::relative(zone)
+    } else {
+        let default_format = if options.group_by_day {
+            DEFAULT_GROUPED_DATE_FORMAT
+        } else {
+            DEFAULT_DATE_FORMAT
+        };
+        DateFormatter::new(
+            zone,
+            options.date_format.as_deref().unwrap_or(default_format),
+        )?
+    };
+    let width = match options.width {
+        Some(0) => None,
+        Some(width) => Some(width),
+        None => terminal_width(),
+    };
+
     match options.command {
-        Some(Commands::Add { name, url }) => {
-            if valid_rss_feed_url(&url).is_err() {
-                return Err(Error::InvalidRssFeedUrl(url));
+        Some(Commands::Add {
+            name,
+            url,
+            tags,
+            basic,
+            bearer,
+            headers,
+            scrape_item,
+            scrape_title,
+            scrape_link,
+            scrape_date,
+            scrape_date_format,
+        }) => {
+            auth::check_url(&url)?;
+            let auth = Auth::parse(basic.as_deref(), bearer.as_deref(), &headers)?;
+            let credentials = auth.resolve(&db.credentials_file())?;
+            let scrape = ScrapeRules::parse(
+                scrape_item.as_deref(),
+                scrape_title.as_deref(),
+                scrape_link.as_deref(),
+                scrape_date.as_deref(),
+                scrape_date_format.as_deref(),
+            )?;
+            match &scrape {
+                Some(rules) => {
+                    if rules.fetch_items(&client, &url, &credentials)?.is_empty() {
+                        return Err(Error::NothingScraped(url));
+                    }
+                }
+                None => match valid_rss_feed_url(&client, &url, &credentials) {
+                    Ok(()) => {}
+                    // Problems with the proxy say nothing about the feed itself.
+                    Err(error @ (Error::ProxyConnect(_) | Error::ProxyUnauthorized(_))) => {
+                        return Err(error)
+                    }
+                    Err(_) => return Err(Error::InvalidRssFeedUrl(url)),
+                },
             }
 
-            db.add(name.clone(), url.clone())?;
+            let mut subscription = Subscription::new(url.clone());
+            if let Some(tags) = tags {
+                subscription.tags = parse_tags(&tags);
+            }
+            subscription.auth = auth;
+            subscription.scrape = scrape;
+            db.add(name.clone(), subscription)?;
 
             writeln!(
                 &mut stdout,
@@ -171,9 +866,584 @@ fn run() -> Result<()> {
                 )?,
             }
         }
+        Some(Commands::Download { name, latest, dir }) => {
+            let feed = db.fetch_feed(&client, &name, &filter)?;
+            download::download_enclosures(&mut stdout, &client, &feed, latest, &dir)?;
+        }
+        Some(Commands::Auth {
+            name,
+            basic,
+            bearer,
+            headers,
+        }) => {
+            let auth = Auth::parse(basic.as_deref(), bearer.as_deref(), &headers)?;
+            if db.set_auth(&name, auth)? {
+                writeln!(&mut stdout, "Updated the credentials of '{}'", name)?;
+            } else {
+                writeln!(
+                    &mut stdout,
+                    "Failed to update the credentials of '{}' as you are not subscribed to that \
+                     feed",
+                    name
+                )?;
+            }
+        }
+        Some(Commands::Credential { key, remove }) => {
+            let credentials_file = db.credentials_file();
+            match key {
+                Some(key) if remove => {
+                    if store_credential(&credentials_file, &key, None)? {
+                        writeln!(&mut stdout, "Removed the secret '{}'", key)?;
+                    } else {
+                        writeln!(&mut stdout, "There is no secret named '{}'", key)?;
+                    }
+                }
+                Some(key) => {
+                    let mut secret = String::new();
+                    std::io::stdin().read_line(&mut secret)?;
+                    store_credential(&credentials_file, &key, Some(secret.trim_end()))?;
+                    writeln!(
+                        &mut stdout,
+                        "Stored the secret '{}', reference it as file:{}",
+                        key, key
+                    )?;
+                }
+                None => {
+                    let keys = credential_keys(&credentials_file)?;
+                    if keys.is_empty() {
+                        writeln!(&mut stdout, "No secrets stored yet")?;
+                    }
+                    for key in keys {
+                        writeln!(&mut stdout, "{}", key)?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Hook { name, command }) => {
+            if db.set_hook(&name, command)? {
+                writeln!(&mut stdout, "Updated the hook of '{}'", name)?;
+            } else {
+                writeln!(
+                    &mut stdout,
+                    "Failed to update the hook of '{}' as you are not subscribed to that feed",
+                    name
+                )?;
+            }
+        }
+        Some(Commands::Interval { name, interval }) => {
+            if db.set_interval(&name, interval)? {
+                writeln!(&mut stdout, "Updated the interval of '{}'", name)?;
+            } else {
+                writeln!(
+                    &mut stdout,
+                    "Failed to update the interval of '{}' as you are not subscribed to that feed",
+                    name
+                )?;
+            }
+        }
+        Some(Commands::Rules {
+            name,
+            include,
+            exclude,
+        }) => {
+            if db.set_rules(&name, include, exclude)? {
+                writeln!(&mut stdout, "Updated the rules of '{}'", name)?;
+            } else {
+                writeln!(
+                    &mut stdout,
+                    "Failed to update the rules of '{}' as you are not subscribed to that feed",
+                    name
+                )?;
+            }
+        }
+        Some(Commands::Tag { name, tags }) => {
+            if db.set_tags(&name, parse_tags(&tags))? {
+                writeln!(&mut stdout, "Updated the tags of '{}'", name)?;
+            } else {
+                writeln!(
+                    &mut stdout,
+                    "Failed to tag '{}' as you are not subscribed to that feed",
+                    name
+                )?;
+            }
+        }
+        Some(Commands::Webhook { command }) => {
+            let mut webhooks = Webhooks::next_to(db.path())?;
+            match command {
+                WebhookCommands::Add {
+                    name,
+                    url,
+                    format,
+                    feeds,
+                    tags,
+                } => {
+                    let mut webhook = Webhook::new(url.clone(), format);
+                    if let Some(feeds) = feeds {
+                        webhook.feeds = parse_names(&feeds);
+                    }
+                    if let Some(tags) = tags {
+                        webhook.tags = parse_tags(&tags);
+                    }
+                    webhooks.add(name.clone(), webhook)?;
+
+                    writeln!(&mut stdout, "Added webhook '{}' with url '{}'", name, url)?;
+                }
+                WebhookCommands::List => {
+                    if webhooks.webhooks().is_empty() {
+                        writeln!(&mut stdout, "No webhooks added yet")?;
+                    }
+
+                    for (name, webhook) in webhooks.webhooks() {
+                        write!(&mut stdout, "{} - {} ({})", name, webhook.url, webhook.format)?;
+                        if !webhook.feeds.is_empty() {
+                            let feeds: Vec<_> = webhook.feeds.iter().cloned().collect();
+                            write!(&mut stdout, ", feeds: {}", feeds.join(", "))?;
+                        }
+                        if !webhook.tags.is_empty() {
+                            let tags: Vec<_> = webhook.tags.iter().cloned().collect();
+                            write!(&mut stdout, ", tags: {}", tags.join(", "))?;
+                        }
+                        writeln!(&mut stdout)?;
+                    }
+                }
+                WebhookCommands::Remove { name } => match webhooks.remove(&name)? {
+                    Some(_) => writeln!(&mut stdout, "Removed webhook '{}'", name)?,
+                    None => writeln!(
+                        &mut stdout,
+                        "Failed to remove webhook '{}' as there is no webhook with that name",
+                        name
+                    )?,
+                },
+                WebhookCommands::Test { name } => {
+                    webhooks.send_test(&client, &name)?;
+                    writeln!(&mut stdout, "Posted a test post to webhook '{}'", name)?;
+                }
+            }
+        }
+        Some(Commands::Open {
+            target,
+            number,
+            id,
+            dry_run,
+        }) => {
+            let url = match (target, id) {
+                (target, Some(id)) => {
+                    let feeds = match target {
+                        Some(name) => vec![db.fetch_feed(&client, &name, &filter)?],
+                        None => db.collect_feeds_with_items_since(
+                            &client,
+                            &TimeWindow::all_time(),
+                            true,
+                            &filter,
+                        ),
+                    };
+                    let (feed, item) = find_post_by_id(&feeds, &id)?;
+                    feed.post_url(item)?.to_string()
+                }
+                (Some(target), None) => match target.parse::<usize>() {
+                    // Blogs may be named after numbers, in which case the blog is opened.
+                    Ok(listed) if number.is_none() && !db.subscriptions().contains_key(&target) => {
+                        Listing::next_to(db.path())?
+                            .get(listed)
+                            .map(|item| item.url.clone())
+                            .ok_or(Error::UnknownPostNumber(listed))?
+                    }
+                    _ => {
+                        let feed = db.fetch_feed(&client, &target, &filter)?;
+                        let index = number.unwrap_or(1) as usize - 1;
+                        let item = feed.items.get(index).ok_or(Error::NotEnoughPosts {
+                            name: target,
+                            count: feed.items.len(),
+                        })?;
+                        feed.post_url(item)?.to_string()
+                    }
+                },
+                (None, None) => unreachable!("clap requires either a target or an id"),
+            };
+
+            if dry_run {
+                writeln!(&mut stdout, "{}", opener.describe(&url))?;
+            } else {
+                opener.open(&url)?;
+                writeln!(&mut stdout, "Opened {}", url)?;
+            }
+        }
+        Some(Commands::Publish {
+            out,
+            format,
+            title,
+            url,
+        }) => {
+            let window = time_window(
+                options.since.as_deref(),
+                options.until.as_deref(),
+                options.new_only,
+                options.weeks,
+            )?;
+            let mut feeds = db.collect_feeds_with_items_since(&client, &window, true, &filter);
+            if options.hide_updates {
+                remove_updates(&mut feeds, &window);
+            }
+            if !options.no_dedupe {
+                dedupe(&mut feeds, options.fuzzy_titles);
+            }
+            let entries = combine(&feeds, options.posts_limit);
+
+            // Without a url, the feed still needs a permanent id, which is derived from its title.
+            let id = url.clone().unwrap_or_else(|| {
+                format!(
+                    "urn:gobbler:{}",
+                    utf8_percent_encode(&title, NON_ALPHANUMERIC)
+                )
+            });
+            let info = FeedInfo {
+                title: &title,
+                id: &id,
+                self_url: url.as_deref(),
+            };
+            std::fs::write(&out, to_feed(format, &info, &entries)?)?;
+
+            writeln!(
+                &mut stdout,
+                "Published {} posts to {}",
+                entries.len(),
+                out.display()
+            )?;
+        }
+        Some(Commands::ScrapeTest {
+            source,
+            scrape_item,
+            scrape_title,
+            scrape_link,
+            scrape_date,
+            scrape_date_format,
+        }) => {
+            let rules = ScrapeRules::parse(
+                Some(&scrape_item),
+                Some(&scrape_title),
+                Some(&scrape_link),
+                Some(&scrape_date),
+                scrape_date_format.as_deref(),
+            )?
+            .ok_or(Error::IncompleteScrapeRules)?;
+
+            let url = page_url(&source)?;
+            let page = scrape::fetch_page(&client, url.as_str(), &Credentials::default())?;
+            let items = rules.extract(&page, &url)?;
+            if items.is_empty() {
+                writeln!(&mut stdout, "Nothing on the page matches '{}'", rules.item)?;
+                return Ok(());
+            }
+
+            for (number, item) in items.iter().enumerate() {
+                let title = match &item.title {
+                    Some(title) => title.clone(),
+                    None => format!("(nothing matches '{}')", rules.title),
+                };
+                let link = match &item.link {
+                    Some(link) => link.to_string(),
+                    None => format!("(nothing with a href matches '{}')", rules.link),
+                };
+                let date = match &item.date {
+                    Some((text, Some(timestamp))) => {
+                        format!("{} (from '{}')", dates.format(*timestamp), text)
+                    }
+                    Some((text, None)) => format!("(failed to parse '{}')", text),
+                    None => format!("(nothing matches '{}')", rules.date),
+                };
+
+                writeln!(&mut stdout, "{}. {}", number + 1, title)?;
+                writeln!(&mut stdout, "    link: {}", link)?;
+                writeln!(&mut stdout, "    date: {}", date)?;
+            }
+
+            let complete = items.iter().filter(|item| item.is_complete()).count();
+            writeln!(
+                &mut stdout,
+                "\n{} of the {} posts on the page have a title, link and date, and would be shown",
+                complete,
+                items.len()
+            )?;
+        }
+        Some(Commands::Search {
+            query,
+            feed,
+            since,
+            results,
+        }) => {
+            let query = query.join(" ");
+            let since = since
+                .map(|since| PointInTime::parse(&since, Local::now()))
+                .transpose()?
+                .map(|since| since.start);
+
+            let display_options = DisplayOptions {
+                limit: results,
+                count_only: false,
+                summary: false,
+                hyperlinks,
+                urls: options.urls,
+                width,
+                dates,
+                group_by_day: false,
+                marks: None,
+            };
+
+            let items = Archive::next_to(db.path())?.search(&query, feed.as_deref(), since, results)?;
+            if items.is_empty() {
+                writeln!(&mut stdout, "No posts found matching '{}'", query)?;
+            }
+            for item in &items {
+                item.print_colored(&mut stdout, &display_options)?;
+            }
+        }
+        Some(Commands::Stats { json }) => {
+            let archive = Archive::next_to(db.path())?;
+            let now = Utc::now();
+
+            let mut stats = vec![];
+            let mut all_timestamps = vec![];
+            let mut all_fetches = FetchHistory::default();
+            for (name, subscription) in db.subscriptions() {
+                if !filter.matches(name, subscription) {
+                    continue;
+                }
+
+                let timestamps = archive.post_timestamps(name)?;
+                let fetches = archive.fetch_history(name)?;
+                stats.push(FeedStats::new(name.clone(), &timestamps, fetches, now));
+                all_timestamps.extend(timestamps);
+                all_fetches = all_fetches + fetches;
+            }
+            let overall = FeedStats::new(
+                String::from("All feeds"),
+                &all_timestamps,
+                all_fetches,
+                now,
+            );
+
+            if json {
+                let exported = serde_json::json!({
+                    "feeds": stats.iter().map(FeedStats::to_exported).collect::<Vec<_>>(),
+                    "overall": overall.to_exported(),
+                });
+                writeln!(&mut stdout, "{}", serde_json::to_string_pretty(&exported)?)?;
+            } else {
+                for feed in &stats {
+                    feed.print_colored(&mut stdout, &dates)?;
+                }
+                if stats.len() > 1 {
+                    overall.print_colored(&mut stdout, &dates)?;
+                }
+            }
+        }
+        Some(Commands::Star { target, feed }) => {
+            let item = find_post_to_star(&db, &client, &filter, feed, &target)?;
+            let title = item.title.clone();
+            if Marks::next_to(db.path())?.star(item)? {
+                writeln!(&mut stdout, "Starred '{}'", title)?;
+            } else {
+                writeln!(&mut stdout, "'{}' was already starred", title)?;
+            }
+        }
+        Some(Commands::Starred { export, out }) => {
+            let marks = Marks::next_to(db.path())?;
+            match export {
+                Some(format) => {
+                    let exported = bookmarks::export(format, marks.starred())?;
+                    match out {
+                        Some(out) => {
+                            std::fs::write(&out, exported)?;
+                            writeln!(
+                                &mut stdout,
+                                "Exported {} starred posts to {}",
+                                marks.starred().count(),
+                                out.display()
+                            )?;
+                        }
+                        None => write!(&mut stdout, "{}", exported)?,
+                    }
+                }
+                None => {
+                    let display_options = DisplayOptions {
+                        limit: usize::MAX,
+                        count_only: false,
+                        summary: false,
+                        hyperlinks,
+                        urls: options.urls,
+                        width,
+                        dates,
+                        group_by_day: false,
+                        marks: None,
+                    };
+
+                    let mut starred: Vec<_> = marks.starred().collect();
+                    starred.sort_by_key(|item| Reverse(item.timestamp));
+                    if starred.is_empty() {
+                        writeln!(&mut stdout, "No starred posts")?;
+                    }
+                    for item in starred {
+                        item.print_colored(&mut stdout, &display_options)?;
+                    }
+                }
+            }
+        }
+        Some(Commands::Unstar { target, feed }) => {
+            let mut marks = Marks::next_to(db.path())?;
+            let found = match target.parse::<usize>() {
+                Ok(number) => {
+                    let listing = Listing::next_to(db.path())?;
+                    let listed = listing
+                        .get(number)
+                        .ok_or(Error::UnknownPostNumber(number))?;
+                    marks.find_starred(Some(&listed.feed), &listed.id)
+                }
+                Err(_) => marks.find_starred(feed.as_deref(), &target),
+            }
+            .map(|item| (item.feed.clone(), item.id.clone(), item.title.clone()));
+
+            match found {
+                Some((feed, id, title)) => {
+                    marks.unstar(&feed, &id)?;
+                    writeln!(&mut stdout, "Unstarred '{}'", title)?;
+                }
+                None => writeln!(
+                    &mut stdout,
+                    "Failed to unstar '{}' as there is no starred post with that id",
+                    target
+                )?,
+            }
+        }
+        Some(Commands::Serve { port, host, cache }) => {
+            let cache = parse_interval(&cache)?;
+            let shutdown = handle_shutdown_signals()?;
+
+            let address = format!("{}:{}", host, port);
+            writeln!(
+                &mut stdout,
+                "Serving posts on http://{}, press Ctrl+C to stop",
+                address
+            )?;
+            Server::new(
+                &db,
+                &client,
+                &filter,
+                options.weeks,
+                options.posts_limit,
+                cache,
+            )
+            .run(&address, &shutdown)?;
+        }
+        Some(Commands::Show { source }) => {
+            let window = time_window(
+                options.since.as_deref(),
+                options.until.as_deref(),
+                options.new_only,
+                options.weeks,
+            )?;
+            let display_options = DisplayOptions {
+                limit: options.posts_limit,
+                count_only: options.count_only,
+                summary: options.summary,
+                hyperlinks,
+                urls: options.urls,
+                width,
+                dates,
+                group_by_day: options.group_by_day,
+                marks: None,
+            };
+
+            let mut feed = Feed::read(&client, &source, &window, &filter)?;
+            if options.hide_updates {
+                remove_updates(std::slice::from_mut(&mut feed), &window);
+            }
+            feed.print_colored(&mut stdout, &window, &display_options, None)?;
+        }
+        Some(Commands::Tui) => {
+            let window = time_window(
+                options.since.as_deref(),
+                options.until.as_deref(),
+                options.new_only,
+                options.weeks,
+            )?;
+            let mut marks = Marks::next_to(db.path())?;
+            Tui::new(&db, &client, &filter, &opener, &dates, window, &mut marks).run()?;
+        }
+        Some(Commands::Watch { interval }) => {
+            let interval = parse_interval(&interval)?;
+            let shutdown = handle_shutdown_signals()?;
+
+            let display_options = DisplayOptions {
+                limit: options.posts_limit,
+                count_only: false,
+                summary: options.summary,
+                hyperlinks,
+                urls: options.urls,
+                width,
+                dates,
+                group_by_day: options.group_by_day,
+                marks: Some(Marks::next_to(db.path())?),
+            };
+            let window = TimeWindow::all_time();
+            let mut listing = Listing::next_to(db.path())?;
+            listing.clear();
+            let mut hook_log = HookLog::next_to(db.path())?;
+            let webhooks = Webhooks::next_to(db.path())?;
+            let mut delivery_log = DeliveryLog::next_to(db.path())?;
+
+            writeln!(
+                &mut stdout,
+                "Watching for new posts every {}, press Ctrl+C to stop",
+                format_interval(interval)
+            )?;
+            Watcher::new(interval).run(
+                &client,
+                &db,
+                &filter,
+                &shutdown,
+                |event| match event {
+                    WatchEvent::NewItems(feed) => {
+                        if options.number {
+                            feed.print_colored(
+                                &mut stdout,
+                                &window,
+                                &display_options,
+                                Some(&mut listing),
+                            )?;
+                            listing.save()?;
+                        } else {
+                            feed.print_colored(&mut stdout, &window, &display_options, None)?;
+                        }
+                        handle_new_items(
+                            &hooks,
+                            &mut hook_log,
+                            &webhooks,
+                            &mut delivery_log,
+                            &client,
+                            &db,
+                            &feed,
+                        );
+                        Ok(())
+                    }
+                    WatchEvent::PollFailed {
+                        name,
+                        error,
+                        retry_in,
+                    } => {
+                        eprintln!(
+                            "WARNING: Failed to poll '{}' ({}), retrying in {}",
+                            name,
+                            error,
+                            format_interval(retry_in)
+                        );
+                        Ok(())
+                    }
+                },
+            )?;
+        }
         None => {
             if options.list {
-                db.print_subscriptions(&mut stdout)?;
+                db.print_subscriptions(&mut stdout, &filter)?;
             } else if options.last_ran_at {
                 let last_ran_at: DateTime<Local> = DateTime::from(get_last_ran_at()?);
                 writeln!(
@@ -192,32 +1462,78 @@ fn run() -> Result<()> {
                 }
 
                 let mut found_items = false;
-                let since = if options.new_only {
-                    // Only show new posts
-                    get_last_ran_at()?
+                let window = time_window(
+                    options.since.as_deref(),
+                    options.until.as_deref(),
+                    options.new_only,
+                    options.weeks,
+                )?;
+
+                let display_options = DisplayOptions {
+                    limit: options.posts_limit,
+                    count_only: options.count_only,
+                    summary: options.summary,
+                    hyperlinks,
+                    urls: options.urls,
+                    width,
+                    dates,
+                    group_by_day: options.group_by_day,
+                    marks: Some(Marks::next_to(db.path())?),
+                };
+
+                let mut listing = Listing::next_to(db.path())?;
+                listing.clear();
+
+                let mut feeds = db.collect_feeds_with_items_since(
+                    &client,
+                    &window,
+                    options.hide_empty_feeds,
+                    &filter,
+                );
+                if options.hide_updates {
+                    remove_updates(&mut feeds, &window);
+                }
+                if !options.no_dedupe {
+                    dedupe(&mut feeds, options.fuzzy_titles);
+                }
+                if options.hide_empty_feeds {
+                    feeds.retain(|feed| !feed.items.is_empty());
+                }
+
+                // Hooks and webhooks only run for new posts, and their logs keep them from running
+                // for a post again every time gobbler runs.
+                let mut webhooks = if options.new_only {
+                    Some((
+                        HookLog::next_to(db.path())?,
+                        Webhooks::next_to(db.path())?,
+                        DeliveryLog::next_to(db.path())?,
+                    ))
                 } else {
-                    Utc::now().sub(Duration::weeks(options.weeks))
+                    None
                 };
 
-                for feed in db
-                    .collect_feeds_with_items_since(
-                        &SyndicationClient::new()?,
-                        since,
-                        options.hide_empty_feeds,
-                        options.filter_by_name,
-                    )
-                    .iter()
-                {
-                    feed.print_colored(
-                        &mut stdout,
-                        options.weeks,
-                        options.posts_limit, 
-                        options.count_only
-                    )?;
+                for feed in &feeds {
+                    let listing = options.number.then_some(&mut listing);
+                    feed.print_colored(&mut stdout, &window, &display_options, listing)?;
+                    if let Some((hook_log, webhooks, delivery_log)) = &mut webhooks {
+                        handle_new_items(
+                            &hooks,
+                            hook_log,
+                            webhooks,
+                            delivery_log,
+                            &client,
+                            &db,
+                            feed,
+                        );
+                    }
 
                     found_items = true;
                 }
 
+                if options.number {
+                    listing.save()?;
+                }
+
                 // Only save whether we ran today if it's applicable AND we have found items. This
                 // last condition handles running the program while not connected to the internet,
                 // in which case no posts will be found.
@@ -231,13 +1547,169 @@ fn run() -> Result<()> {
     Ok(())
 }
 
-/// Check whether `url` is a valid RSS feed url.
-fn valid_rss_feed_url(url: &str) -> Result<()> {
-    let uri = Uri::CreateUri(&HSTRING::from(url))?;
-    let client = SyndicationClient::new()?;
-    let feed = client.RetrieveFeedAsync(&uri)?.get()?;
+/// Check whether `command` fetches the feeds which are subscribed to, as only those commands
+/// store the posts they fetch in the archive.
+fn fetches_feeds(command: &Option<Commands>) -> bool {
+    matches!(
+        command,
+        None | Some(
+            Commands::Download { .. }
+                | Commands::Open { .. }
+                | Commands::Publish { .. }
+                | Commands::Serve { .. }
+                | Commands::Star { .. }
+                | Commands::Tui
+                | Commands::Watch { .. }
+        )
+    )
+}
+
+/// Get the post in `feeds` whose id or url is `id`, together with its feed.
+fn find_post_by_id<'a>(feeds: &'a [Feed], id: &str) -> Result<(&'a Feed, &'a FeedItem)> {
+    for feed in feeds {
+        for item in &feed.items {
+            if item.id == id || feed.post_url(item)?.as_str() == id {
+                return Ok((feed, item));
+            }
+        }
+    }
+
+    Err(Error::UnknownPostId(id.to_string()))
+}
+
+/// Find the post to star, which is either the post numbered `target` by --number, or the post
+/// whose id or url is `target`. Posts are looked up in the archive first, so that posts which
+/// have dropped out of their feed can be starred as well, and in the feeds otherwise.
+fn find_post_to_star(
+    db: &Database,
+    client: &HttpClient,
+    filter: &Filter,
+    feed: Option<String>,
+    target: &str,
+) -> Result<StarredItem> {
+    let (feed, id) = match target.parse::<usize>() {
+        Ok(number) => {
+            let listing = Listing::next_to(db.path())?;
+            let listed = listing
+                .get(number)
+                .ok_or(Error::UnknownPostNumber(number))?;
+            (Some(listed.feed.clone()), listed.id.clone())
+        }
+        Err(_) => (feed, target.to_string()),
+    };
+
+    if let Some(item) = Archive::next_to(db.path())?.find(feed.as_deref(), &id)? {
+        return Ok(StarredItem {
+            feed: item.feed,
+            id: item.id,
+            title: item.title,
+            url: item.url,
+            timestamp: item.timestamp,
+        });
+    }
+
+    let feeds = match feed {
+        Some(name) => vec![db.fetch_feed(client, &name, filter)?],
+        None => db.collect_feeds_with_items_since(client, &TimeWindow::all_time(), true, filter),
+    };
+    let (feed, item) = find_post_by_id(&feeds, &id)?;
+
+    StarredItem::new(feed, item)
+}
+
+/// Remove the posts from `feeds` which were first published before `window`, and only fall within
+/// it because they were updated since.
+fn remove_updates(feeds: &mut [Feed], window: &TimeWindow) {
+    for feed in feeds {
+        feed.items.retain(|item| !item.is_updated_within(window));
+    }
+}
+
+/// Get the window of time from which posts are shown, as selected by --since, --until,
+/// --new-only and --weeks.
+fn time_window(
+    since: Option<&str>,
+    until: Option<&str>,
+    new_only: bool,
+    weeks: i64,
+) -> Result<TimeWindow> {
+    let now = Local::now();
+    let until = until
+        .map(|until| PointInTime::parse(until, now))
+        .transpose()?
+        .map(|until| until.end);
+    if let Some(since) = since {
+        return Ok(TimeWindow::between(
+            PointInTime::parse(since, now)?.start,
+            until,
+        ));
+    }
+
+    let window = if new_only {
+        // Only show new posts
+        TimeWindow::since_last_run(get_last_ran_at()?)
+    } else {
+        TimeWindow::last_weeks(weeks)
+    };
+
+    Ok(match until {
+        Some(until) => window.with_until(until),
+        None => window,
+    })
+}
+
+/// Install a handler for Ctrl+C and termination signals, returning the flag which is set when
+/// one is received. A second Ctrl+C stops `gobbler` right away.
+fn handle_shutdown_signals() -> Result<Arc<AtomicBool>> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let handler_shutdown = Arc::clone(&shutdown);
+    ctrlc::set_handler(move || {
+        if handler_shutdown.swap(true, Ordering::SeqCst) {
+            exit(130);
+        }
+    })?;
+
+    Ok(shutdown)
+}
+
+/// Run the hooks for the new items in `feed` and deliver them to the webhooks, reporting any hooks
+/// and deliveries which failed.
+fn handle_new_items(
+    hooks: &HookRunner,
+    hook_log: &mut HookLog,
+    webhooks: &Webhooks,
+    delivery_log: &mut DeliveryLog,
+    client: &HttpClient,
+    db: &Database,
+    feed: &Feed,
+) {
+    let Some(subscription) = db.subscriptions().get(feed.name()) else {
+        return;
+    };
+
+    for failure in hooks.run(hook_log, feed, subscription.hook.as_deref()) {
+        eprintln!("WARNING: {}", failure);
+    }
+
+    for failure in webhooks.deliver(client, delivery_log, feed, subscription) {
+        eprintln!("WARNING: {}", failure);
+    }
+}
+
+/// Get the url of the page passed to `scrape-test`, which is either a url or the path to a saved
+/// HTML file.
+fn page_url(source: &str) -> Result<Url> {
+    match Url::parse(source) {
+        Ok(url) if matches!(url.scheme(), "http" | "https" | "file") => Ok(url),
+        // Paths such as C:\page.html parse as urls with a `c` scheme.
+        _ => Url::from_file_path(std::path::absolute(source)?)
+            .map_err(|_| Error::InvalidRssFeedUrl(source.to_string())),
+    }
+}
 
-    if feed.Items()?.into_iter().next().is_some() {
+/// Check whether `url` is a valid RSS feed url, fetching it with `credentials`.
+fn valid_rss_feed_url(client: &HttpClient, url: &str, credentials: &Credentials) -> Result<()> {
+    if !fetch_items(client, url, credentials)?.is_empty() {
         return Ok(());
     }
 
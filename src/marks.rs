@@ -0,0 +1,227 @@
+//! Marking feed items as read or starred. Marks are stored next to the subscriptions file, so
+//! they are kept between runs.
+
+use crate::{
+    error::*,
+    feed::{print_post, Badges, DisplayOptions, Feed, FeedItem},
+    lines::{escape, read_lines, unescape, write_lines},
+};
+use chrono::{DateTime, FixedOffset};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+use termcolor::StandardStream;
+use url::Url;
+
+/// The file used to store the marked items, which is stored next to the subscriptions file.
+pub const MARKS_FILE: &str = "marks.db";
+
+/// An item which has been starred. Enough of the item is kept to list it after it has dropped out
+/// of its feed.
+pub struct StarredItem {
+    /// The name of the feed the item belongs to
+    pub feed: String,
+
+    /// The id of the item
+    pub id: String,
+
+    /// The title of the item
+    pub title: String,
+
+    /// The url of the item
+    pub url: String,
+
+    /// The timestamp of the item
+    pub timestamp: DateTime<FixedOffset>,
+}
+
+impl StarredItem {
+    /// Create a new [`StarredItem`] for `item`, an item of `feed`.
+    pub fn new(feed: &Feed, item: &FeedItem) -> Result<Self> {
+        Ok(Self {
+            feed: feed.name().to_string(),
+            id: item.id.clone(),
+            title: item.title.clone(),
+            url: feed.post_url(item)?.to_string(),
+            timestamp: item.timestamp,
+        })
+    }
+
+    /// Writes the starred item to the given [`StandardStream`].
+    pub fn print_colored(
+        &self,
+        stdout: &mut StandardStream,
+        options: &DisplayOptions,
+    ) -> Result<()> {
+        let timestamp = format!("{} · {}", options.dates.format(self.timestamp), self.feed);
+        print_post(
+            stdout,
+            "    ",
+            &timestamp,
+            &self.title,
+            &Url::parse(&self.url)?,
+            Badges::default(),
+            options,
+        )
+    }
+
+    /// Parse a starred item from a line of the marks file, which is the part following `starred`.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(5, '\t');
+        let feed = unescape(fields.next()?);
+        let id = unescape(fields.next()?);
+        let timestamp = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+        let url = unescape(fields.next()?);
+        let title = unescape(fields.next()?);
+
+        Some(Self {
+            feed,
+            id,
+            title,
+            url,
+            timestamp,
+        })
+    }
+
+    /// Convert the starred item to a line of the marks file. Every field can contain anything, but
+    /// has to fit on a single line.
+    fn to_line(&self) -> String {
+        format!(
+            "starred\t{}\t{}\t{}\t{}\t{}",
+            escape(&self.feed),
+            escape(&self.id),
+            self.timestamp.to_rfc3339(),
+            escape(&self.url),
+            escape(&self.title)
+        )
+    }
+}
+
+/// The items which have been marked as read or starred.
+pub struct Marks {
+    /// The path to the marks file
+    path: PathBuf,
+
+    /// The keys of the items which have been read, see [`key`]
+    read: HashSet<String>,
+
+    /// The items which have been starred, by key, see [`key`]
+    starred: BTreeMap<String, StarredItem>,
+}
+
+impl Marks {
+    /// Load the marked items from the marks file next to `subscriptions_file`.
+    pub fn next_to(subscriptions_file: &Path) -> Result<Self> {
+        let path = subscriptions_file.with_file_name(MARKS_FILE);
+
+        let mut read = HashSet::new();
+        let mut starred = BTreeMap::new();
+        for line in read_lines(&path)? {
+            if let Some(item) = line.strip_prefix("read\t") {
+                read.insert(item.to_string());
+            } else if let Some(item) = line.strip_prefix("starred\t").and_then(StarredItem::parse) {
+                starred.insert(key(&item.feed, &item.id), item);
+            }
+        }
+
+        Ok(Self {
+            path,
+            read,
+            starred,
+        })
+    }
+
+    /// Check whether `item`, an item of the feed named `feed`, has been read.
+    pub fn is_read(&self, feed: &str, item: &FeedItem) -> bool {
+        self.read.contains(&key(feed, &item.id))
+    }
+
+    /// Mark `item`, an item of `feed`, as read or unread.
+    pub fn set_read(&mut self, feed: &Feed, item: &FeedItem, read: bool) -> Result<()> {
+        let key = key(feed.name(), &item.id);
+        let changed = if read {
+            self.read.insert(key)
+        } else {
+            self.read.remove(&key)
+        };
+
+        if changed {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Check whether `item`, an item of the feed named `feed`, has been starred.
+    pub fn is_starred(&self, feed: &str, item: &FeedItem) -> bool {
+        self.starred.contains_key(&key(feed, &item.id))
+    }
+
+    /// Star or unstar `item`, an item of `feed`.
+    pub fn set_starred(&mut self, feed: &Feed, item: &FeedItem, starred: bool) -> Result<()> {
+        if starred {
+            self.star(StarredItem::new(feed, item)?)?;
+        } else {
+            self.unstar(feed.name(), &item.id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Star `item`. Returns `false` if the item was already starred, in which case the stored
+    /// item is updated.
+    pub fn star(&mut self, item: StarredItem) -> Result<bool> {
+        let starred = self
+            .starred
+            .insert(key(&item.feed, &item.id), item)
+            .is_none();
+        self.save()?;
+
+        Ok(starred)
+    }
+
+    /// Unstar the item with the given id, of the feed named `feed`. Returns `false` if the item
+    /// was not starred.
+    pub fn unstar(&mut self, feed: &str, id: &str) -> Result<bool> {
+        if self.starred.remove(&key(feed, id)).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+
+        Ok(true)
+    }
+
+    /// Find the starred item whose id or url is `id`, only looking at the items of the feed
+    /// named `feed` if given.
+    pub fn find_starred(&self, feed: Option<&str>, id: &str) -> Option<&StarredItem> {
+        self.starred
+            .values()
+            .filter(|item| feed.is_none() || feed == Some(item.feed.as_str()))
+            .find(|item| item.id == id || item.url == id)
+    }
+
+    /// Get the starred items, ordered by feed.
+    pub fn starred(&self) -> impl Iterator<Item = &StarredItem> {
+        self.starred.values()
+    }
+
+    /// Write all marks to the marks file, replacing its current contents.
+    fn save(&self) -> Result<()> {
+        let mut read: Vec<_> = self.read.iter().collect();
+        read.sort();
+
+        write_lines(
+            &self.path,
+            read.into_iter()
+                .map(|key| format!("read\t{}", key))
+                .chain(self.starred.values().map(StarredItem::to_line)),
+        )
+    }
+}
+
+/// Get the key under which the item with the given id, of the feed named `feed`, is stored. Read
+/// items are stored in the marks file by key, so both are escaped.
+fn key(feed: &str, id: &str) -> String {
+    format!("{}\t{}", escape(feed), escape(id))
+}
@@ -0,0 +1,144 @@
+//! Functions for importing and exporting subscriptions as OPML, the format most feed readers use to
+//! exchange their subscriptions.
+
+use crate::{
+    error::*,
+    feed::{normalize_tag, Database, Subscription},
+    source,
+    xml::{escape_xml, get_attribute},
+};
+use std::{fs, path::Path};
+use windows::{
+    core::HSTRING,
+    Data::Xml::Dom::{IXmlNode, XmlDocument},
+};
+
+/// Check whether `file` is an OPML file, based on its extension.
+pub fn is_opml_file(file: &Path) -> bool {
+    file.extension()
+        .map(|extension| {
+            extension.eq_ignore_ascii_case("opml") || extension.eq_ignore_ascii_case("xml")
+        })
+        .unwrap_or(false)
+}
+
+/// Read the subscriptions listed in the OPML file `file`. The categories of an outline, as well as
+/// the titles of the outlines it is nested in, are mapped onto tags.
+pub fn read_subscriptions(file: &Path) -> Result<Vec<(String, Subscription)>> {
+    let doc = XmlDocument::new()?;
+    doc.LoadXml(&HSTRING::from(fs::read_to_string(file)?))?;
+
+    let body = doc
+        .GetElementsByTagName(&HSTRING::from("body"))?
+        .Item(0)
+        .map_err(|_| Error::InvalidOpmlFile(file.display().to_string()))?;
+
+    let mut subscriptions = vec![];
+    collect_outlines(&body, &mut vec![], &mut subscriptions)?;
+
+    Ok(subscriptions)
+}
+
+/// Collect the subscriptions from the outlines nested in `node`. Outlines without a feed url are
+/// treated as folders, whose titles are pushed onto `folders` while visiting their children.
+fn collect_outlines(
+    node: &IXmlNode,
+    folders: &mut Vec<String>,
+    subscriptions: &mut Vec<(String, Subscription)>,
+) -> Result<()> {
+    for child in node.ChildNodes()? {
+        if child.NodeName()?.to_string_lossy() != "outline" {
+            continue;
+        }
+
+        let title = get_attribute(&child, "text").or_else(|| get_attribute(&child, "title"));
+        match get_attribute(&child, "xmlUrl") {
+            // Importing a file should never be able to run commands.
+            Some(url) if source::command_of(&url).is_some() => {
+                eprintln!(
+                    "WARNING: Skipping '{}', as feeds printed by commands are never imported",
+                    title.unwrap_or(url)
+                );
+            }
+            Some(url) => {
+                let mut subscription = Subscription::new(url.clone());
+                subscription
+                    .tags
+                    .extend(folders.iter().map(|folder| normalize_tag(folder)));
+
+                // Categories are a comma separated list of slash delimited category paths, of
+                // which only the most specific category is used.
+                if let Some(categories) = get_attribute(&child, "category") {
+                    subscription.tags.extend(
+                        categories
+                            .split(',')
+                            .filter_map(|category| category.rsplit('/').next())
+                            .map(normalize_tag)
+                            .filter(|tag| !tag.is_empty()),
+                    );
+                }
+
+                subscriptions.push((to_name(&title.unwrap_or(url)), subscription));
+            }
+            None => {
+                if let Some(title) = title {
+                    folders.push(title);
+                    collect_outlines(&child, folders, subscriptions)?;
+                    folders.pop();
+                } else {
+                    collect_outlines(&child, folders, subscriptions)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Turn the title of an outline into the name of a subscription. Names cannot contain commas,
+/// tabs or line breaks, so those are replaced by spaces.
+fn to_name(title: &str) -> String {
+    title
+        .split([',', '\t', '\r', '\n'])
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write the subscriptions in `db` to `file` as OPML. The tags of each subscription are stored in
+/// the `category` attribute of its outline.
+pub fn write_subscriptions(db: &Database, file: &Path) -> Result<()> {
+    let mut opml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <opml version=\"2.0\">\n  \
+           <head>\n    \
+             <title>gobbler subscriptions</title>\n  \
+           </head>\n  \
+           <body>\n",
+    );
+
+    for (name, subscription) in &db.feeds {
+        opml.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{name}\" title=\"{name}\" xmlUrl=\"{url}\"",
+            name = escape_xml(name),
+            url = escape_xml(&subscription.url),
+        ));
+
+        if !subscription.tags.is_empty() {
+            let categories: Vec<_> = subscription
+                .tags
+                .iter()
+                .map(|tag| format!("/{}", escape_xml(tag)))
+                .collect();
+            opml.push_str(&format!(" category=\"{}\"", categories.join(",")));
+        }
+
+        opml.push_str("/>\n");
+    }
+
+    opml.push_str("  </body>\n</opml>\n");
+    fs::write(file, opml)?;
+
+    Ok(())
+}
@@ -0,0 +1,300 @@
+//! Publishing the items of multiple feeds as a single, combined feed.
+
+use crate::{
+    error::*,
+    feed::{Feed, FeedItem},
+    http::is_web_url,
+    xml::escape_xml,
+};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use clap::ValueEnum;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::cmp::Reverse;
+
+/// The URL of the `gobbler` project, which is used where a feed requires a link but none is known.
+const PROJECT_URL: &str = "https://github.com/KnapSac/gobbler";
+
+/// The MIME type used for enclosures without a known type.
+const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// The formats in which a combined feed can be published.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PublishFormat {
+    /// Atom 1.0
+    Atom,
+    /// RSS 2.0
+    Rss,
+}
+
+/// Describes a combined feed.
+pub struct FeedInfo<'a> {
+    /// The title of the combined feed
+    pub title: &'a str,
+
+    /// The unique and permanent id of the combined feed, such as the url it is published at
+    pub id: &'a str,
+
+    /// The url the combined feed is published at, if known
+    pub self_url: Option<&'a str>,
+}
+
+/// Collect the items of `feeds` into a single list, newest first, taking at most `limit` items
+/// from each feed.
+pub fn combine<'a>(
+    feeds: impl IntoIterator<Item = &'a Feed>,
+    limit: usize,
+) -> Vec<(&'a Feed, &'a FeedItem)> {
+    let mut entries: Vec<_> = feeds
+        .into_iter()
+        .flat_map(|feed| feed.items.iter().take(limit).map(move |item| (feed, item)))
+        .collect();
+    entries.sort_by_key(|(_, item)| Reverse(item.timestamp));
+
+    entries
+}
+
+/// Format `entries` as a feed in the given format.
+pub fn to_feed(
+    format: PublishFormat,
+    info: &FeedInfo,
+    entries: &[(&Feed, &FeedItem)],
+) -> Result<String> {
+    match format {
+        PublishFormat::Atom => to_atom(info, entries),
+        PublishFormat::Rss => to_rss(info, entries),
+    }
+}
+
+/// Format `entries` as an Atom 1.0 feed. Every entry has a `source` element which names the feed
+/// it was taken from.
+pub fn to_atom(info: &FeedInfo, entries: &[(&Feed, &FeedItem)]) -> Result<String> {
+    let mut atom = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n",
+    );
+
+    atom.push_str(&format!("  <title>{}</title>\n", escape_xml(info.title)));
+    atom.push_str(&format!("  <id>{}</id>\n", escape_xml(info.id)));
+    atom.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        format_atom_time(last_updated(entries))
+    ));
+    if let Some(self_url) = info.self_url {
+        atom.push_str(&format!(
+            "  <link rel=\"self\" href=\"{}\"/>\n",
+            escape_xml(self_url)
+        ));
+    }
+    // Atom requires an author for every entry, which is taken from the feed if it has none.
+    atom.push_str(&format!(
+        "  <author><name>{}</name></author>\n",
+        escape_xml(info.title)
+    ));
+    atom.push_str(&format!(
+        "  <generator uri=\"{}\">gobbler</generator>\n",
+        PROJECT_URL
+    ));
+
+    for (feed, item) in entries {
+        let url = feed.post_url(item)?.to_string();
+
+        atom.push_str("  <entry>\n");
+        atom.push_str(&format!("    <title>{}</title>\n", escape_xml(&item.title)));
+        atom.push_str(&format!(
+            "    <id>{}</id>\n",
+            escape_xml(&entry_id(feed, item))
+        ));
+        atom.push_str(&format!("    <link href=\"{}\"/>\n", escape_xml(&url)));
+        atom.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            format_atom_time(item.timestamp)
+        ));
+        if let Some(published) = item.published {
+            atom.push_str(&format!(
+                "    <published>{}</published>\n",
+                format_atom_time(published)
+            ));
+        }
+        if let Some(author) = &item.author {
+            atom.push_str(&format!(
+                "    <author><name>{}</name></author>\n",
+                escape_xml(author)
+            ));
+        }
+        for category in &item.categories {
+            atom.push_str(&format!(
+                "    <category term=\"{}\"/>\n",
+                escape_xml(category)
+            ));
+        }
+        if let Some(summary) = &item.summary {
+            atom.push_str(&format!("    <summary>{}</summary>\n", escape_xml(summary)));
+        }
+        for enclosure in &item.enclosures {
+            atom.push_str(&format!(
+                "    <link rel=\"enclosure\" href=\"{}\" type=\"{}\"",
+                escape_xml(&enclosure.url),
+                escape_xml(enclosure.mime_type.as_deref().unwrap_or(DEFAULT_MIME_TYPE)),
+            ));
+            if let Some(length) = enclosure.length {
+                atom.push_str(&format!(" length=\"{}\"", length));
+            }
+            atom.push_str("/>\n");
+        }
+        atom.push_str("    <source>\n");
+        atom.push_str(&format!(
+            "      <id>{}</id>\n",
+            escape_xml(&source_id(feed))
+        ));
+        atom.push_str(&format!(
+            "      <title>{}</title>\n",
+            escape_xml(feed.name())
+        ));
+        if is_web_url(feed.url()) {
+            atom.push_str(&format!(
+                "      <link rel=\"self\" href=\"{}\"/>\n",
+                escape_xml(feed.url())
+            ));
+        }
+        atom.push_str("    </source>\n");
+        atom.push_str("  </entry>\n");
+    }
+
+    atom.push_str("</feed>\n");
+
+    Ok(atom)
+}
+
+/// Format `entries` as a RSS 2.0 feed. Every item has a `source` element which names the feed it
+/// was taken from, and links to it if it is on the web.
+pub fn to_rss(info: &FeedInfo, entries: &[(&Feed, &FeedItem)]) -> Result<String> {
+    let mut rss = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" \
+           xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n  \
+           <channel>\n",
+    );
+
+    rss.push_str(&format!("    <title>{}</title>\n", escape_xml(info.title)));
+    rss.push_str(&format!(
+        "    <link>{}</link>\n",
+        escape_xml(info.self_url.unwrap_or(PROJECT_URL))
+    ));
+    rss.push_str(&format!(
+        "    <description>Posts collected by gobbler for {}</description>\n",
+        escape_xml(info.title)
+    ));
+    rss.push_str(&format!(
+        "    <lastBuildDate>{}</lastBuildDate>\n",
+        last_updated(entries).to_rfc2822()
+    ));
+    rss.push_str("    <generator>gobbler</generator>\n");
+    if let Some(self_url) = info.self_url {
+        rss.push_str(&format!(
+            "    <atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{}\"/>\n",
+            escape_xml(self_url)
+        ));
+    }
+
+    for (feed, item) in entries {
+        let url = feed.post_url(item)?.to_string();
+
+        rss.push_str("    <item>\n");
+        rss.push_str(&format!(
+            "      <title>{}</title>\n",
+            escape_xml(&item.title)
+        ));
+        rss.push_str(&format!("      <link>{}</link>\n", escape_xml(&url)));
+        rss.push_str(&format!(
+            "      <guid isPermaLink=\"false\">{}</guid>\n",
+            escape_xml(&entry_id(feed, item))
+        ));
+        rss.push_str(&format!(
+            "      <pubDate>{}</pubDate>\n",
+            item.timestamp.to_rfc2822()
+        ));
+        // The `author` element of RSS has to be an email address, which is rarely known.
+        if let Some(author) = &item.author {
+            rss.push_str(&format!(
+                "      <dc:creator>{}</dc:creator>\n",
+                escape_xml(author)
+            ));
+        }
+        for category in &item.categories {
+            rss.push_str(&format!(
+                "      <category>{}</category>\n",
+                escape_xml(category)
+            ));
+        }
+        if let Some(summary) = &item.summary {
+            rss.push_str(&format!(
+                "      <description>{}</description>\n",
+                escape_xml(summary)
+            ));
+        }
+        // RSS only allows a single enclosure per item.
+        if let Some(enclosure) = item.enclosures.first() {
+            rss.push_str(&format!(
+                "      <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+                escape_xml(&enclosure.url),
+                enclosure.length.unwrap_or(0),
+                escape_xml(enclosure.mime_type.as_deref().unwrap_or(DEFAULT_MIME_TYPE)),
+            ));
+        }
+        if is_web_url(feed.url()) {
+            rss.push_str(&format!(
+                "      <source url=\"{}\">{}</source>\n",
+                escape_xml(feed.url()),
+                escape_xml(feed.name()),
+            ));
+        } else {
+            rss.push_str(&format!(
+                "      <source>{}</source>\n",
+                escape_xml(feed.name())
+            ));
+        }
+        rss.push_str("    </item>\n");
+    }
+
+    rss.push_str("  </channel>\n</rss>\n");
+
+    Ok(rss)
+}
+
+/// Get the permanent id of `feed`, which is its url if it is on the web. Other urls, such as the
+/// command of `exec:` subscriptions or the path of `file:` urls, are not published, so the id is
+/// derived from the name of the feed instead.
+fn source_id(feed: &Feed) -> String {
+    if is_web_url(feed.url()) {
+        feed.url().to_string()
+    } else {
+        format!(
+            "urn:gobbler:feed:{}",
+            utf8_percent_encode(feed.name(), NON_ALPHANUMERIC)
+        )
+    }
+}
+
+/// Get the permanent id of the entry for `item`, which is derived from the name of its feed and
+/// its id, as the same post may appear in several feeds.
+fn entry_id(feed: &Feed, item: &FeedItem) -> String {
+    format!(
+        "urn:gobbler:post:{}:{}",
+        utf8_percent_encode(feed.name(), NON_ALPHANUMERIC),
+        utf8_percent_encode(&item.id, NON_ALPHANUMERIC)
+    )
+}
+
+/// Get the time the newest of `entries` was updated, or the current time if there are none.
+fn last_updated(entries: &[(&Feed, &FeedItem)]) -> DateTime<FixedOffset> {
+    entries
+        .iter()
+        .map(|(_, item)| item.timestamp)
+        .max()
+        .unwrap_or_else(|| Utc::now().fixed_offset())
+}
+
+/// Format `time` as required by Atom, which is RFC 3339.
+fn format_atom_time(time: DateTime<FixedOffset>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
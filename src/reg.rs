@@ -10,7 +10,7 @@ use winreg::{enums::HKEY_CURRENT_USER, RegKey};
 const REG_VAL_NAME: &str = "LastRanAt";
 
 /// Check if `gobbler` listed feed items in the past `n` days.
-pub(crate) fn ran_in_past_n_days(n: i64) -> Result<bool> {
+pub fn ran_in_past_n_days(n: i64) -> Result<bool> {
     let last_ran = get_last_ran_at()?.date_naive();
     let ran_before = Utc::now().sub(Duration::days(n)).date_naive();
 
@@ -18,14 +18,14 @@ pub(crate) fn ran_in_past_n_days(n: i64) -> Result<bool> {
 }
 
 /// Stores that `gobbler` listed feed items today.
-pub(crate) fn set_ran_today() -> Result<()> {
+pub fn set_ran_today() -> Result<()> {
     get_gobbler_registry_key()?.set_value(REG_VAL_NAME, &(Utc::now().timestamp() as u64))?;
     Ok(())
 }
 
 /// Get the `LastRanAt` value from the Windows registry. If the value has not been set yet,
 /// midnight January 1, 1970 is returned.
-pub(crate) fn get_last_ran_at() -> Result<DateTime<Utc>> {
+pub fn get_last_ran_at() -> Result<DateTime<Utc>> {
     let key = get_gobbler_registry_key()?;
     let last_ran: u64 = match key.get_value(REG_VAL_NAME) {
         Ok(last_ran) => Ok(last_ran),
@@ -0,0 +1,290 @@
+//! Generating feeds for sites which don't have one, by scraping the posts from a page using CSS
+//! selectors.
+
+use crate::{auth::Credentials, error::*, feed::FeedItem, http::HttpClient, source};
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone,
+};
+use scraper::{ElementRef, Html, Selector};
+use std::cmp::Reverse;
+use url::Url;
+
+/// The prefix of the attributes in which scraping rules are stored in the subscriptions file.
+const ATTRIBUTE_PREFIX: &str = "scrape-";
+
+/// The rules used to scrape the posts from a page. Every selector except the one for the items
+/// themselves is matched against an item, or the elements within it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScrapeRules {
+    /// The selector matching the element which contains a single post, such as `article`
+    pub item: String,
+
+    /// The selector matching the element containing the title of a post, such as `h2`
+    pub title: String,
+
+    /// The selector matching the link to a post, such as `a`, whose `href` is used
+    pub link: String,
+
+    /// The selector matching the date of a post, such as `time`
+    pub date: String,
+
+    /// The format of the date, such as `%d %B %Y`. Without a format, dates are expected to be
+    /// RFC 3339 or RFC 2822 timestamps, or `YYYY-MM-DD` dates
+    pub date_format: Option<String>,
+}
+
+impl ScrapeRules {
+    /// Create the rules from the given selectors, checking whether they are valid. Returns `None`
+    /// if no selectors are given at all, as the page is then not scraped.
+    pub fn parse(
+        item: Option<&str>,
+        title: Option<&str>,
+        link: Option<&str>,
+        date: Option<&str>,
+        date_format: Option<&str>,
+    ) -> Result<Option<Self>> {
+        let (item, title, link, date) = match (item, title, link, date) {
+            (None, None, None, None) if date_format.is_none() => return Ok(None),
+            (Some(item), Some(title), Some(link), Some(date)) => (item, title, link, date),
+            _ => return Err(Error::IncompleteScrapeRules),
+        };
+
+        let rules = Self {
+            item: item.to_string(),
+            title: title.to_string(),
+            link: link.to_string(),
+            date: date.to_string(),
+            date_format: date_format.map(str::to_string),
+        };
+        for selector in [&rules.item, &rules.title, &rules.link, &rules.date] {
+            if selector.trim().is_empty() || selector.contains(['\t', '\r', '\n']) {
+                return Err(Error::InvalidSelector(selector.to_string()));
+            }
+        }
+        rules.selectors()?;
+
+        if let Some(format) = &rules.date_format {
+            if format.contains(['\t', '\r', '\n'])
+                || StrftimeItems::new(format).any(|item| item == Item::Error)
+            {
+                return Err(Error::InvalidDateFormat(format.to_string()));
+            }
+        }
+
+        Ok(Some(rules))
+    }
+
+    /// Check whether every selector is set, which is not the case when the subscriptions file
+    /// was edited by hand.
+    pub(crate) fn is_complete(&self) -> bool {
+        [&self.item, &self.title, &self.link, &self.date]
+            .iter()
+            .all(|rule| !rule.is_empty())
+    }
+
+    /// Parse an attribute of a line of the subscriptions file, ignoring attributes which are not
+    /// scraping rules.
+    pub(crate) fn parse_attribute(&mut self, key: &str, value: &str) {
+        let Some(key) = key.strip_prefix(ATTRIBUTE_PREFIX) else {
+            return;
+        };
+
+        match key {
+            "item" => self.item = value.to_string(),
+            "title" => self.title = value.to_string(),
+            "link" => self.link = value.to_string(),
+            "date" => self.date = value.to_string(),
+            "date-format" => self.date_format = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Format the rules as attributes of a line of the subscriptions file.
+    pub(crate) fn to_attributes(&self) -> Vec<String> {
+        let mut attributes = vec![
+            format!("{}item={}", ATTRIBUTE_PREFIX, self.item),
+            format!("{}title={}", ATTRIBUTE_PREFIX, self.title),
+            format!("{}link={}", ATTRIBUTE_PREFIX, self.link),
+            format!("{}date={}", ATTRIBUTE_PREFIX, self.date),
+        ];
+        if let Some(date_format) = &self.date_format {
+            attributes.push(format!("{}date-format={}", ATTRIBUTE_PREFIX, date_format));
+        }
+
+        attributes
+    }
+
+    /// Fetch the page at `url`, sending `credentials` along with the request, and scrape the
+    /// posts from it, see [`fetch_page`].
+    pub fn fetch_items(
+        &self,
+        client: &HttpClient,
+        url: &str,
+        credentials: &Credentials,
+    ) -> Result<Vec<FeedItem>> {
+        let page = fetch_page(client, url, credentials)?;
+
+        self.items(&page, &Url::parse(url)?)
+    }
+
+    /// Scrape the posts from `page`, which was found at `url`, newest first. Posts without a
+    /// title, link or valid date are skipped.
+    pub fn items(&self, page: &str, url: &Url) -> Result<Vec<FeedItem>> {
+        let mut items: Vec<_> = self
+            .extract(page, url)?
+            .into_iter()
+            .filter_map(ScrapedItem::into_feed_item)
+            .collect();
+        // Posts are expected newest first, which isn't necessarily the order of the page.
+        items.sort_by_key(|item| Reverse(item.timestamp));
+
+        Ok(items)
+    }
+
+    /// Extract whatever the rules match in `page`, which was found at `url`, in the order of the
+    /// page, including the posts which are incomplete.
+    pub fn extract(&self, page: &str, url: &Url) -> Result<Vec<ScrapedItem>> {
+        let [item, title, link, date] = self.selectors()?;
+        let page = Html::parse_document(page);
+
+        let items = page
+            .select(&item)
+            .map(|element| {
+                let title = select_first(element, &title)
+                    .map(|title| collapse_whitespace(title.text()))
+                    .filter(|title| !title.is_empty());
+                let link = select_first(element, &link)
+                    .and_then(|link| link.attr("href"))
+                    .and_then(|href| url.join(href.trim()).ok());
+                let date = select_first(element, &date).map(|date| {
+                    // Elements such as `<time>` hold a machine readable date in an attribute.
+                    let text = collapse_whitespace(date.text());
+                    let timestamp = date
+                        .attr("datetime")
+                        .and_then(|datetime| self.parse_date(datetime))
+                        .or_else(|| self.parse_date(&text));
+                    (text, timestamp)
+                });
+
+                ScrapedItem { title, link, date }
+            })
+            .collect();
+
+        Ok(items)
+    }
+
+    /// Parse the selectors for the items, and their title, link and date.
+    fn selectors(&self) -> Result<[Selector; 4]> {
+        let parse = |selector: &str| {
+            Selector::parse(selector).map_err(|_| Error::InvalidSelector(selector.to_string()))
+        };
+
+        Ok([
+            parse(&self.item)?,
+            parse(&self.title)?,
+            parse(&self.link)?,
+            parse(&self.date)?,
+        ])
+    }
+
+    /// Parse the date of a post. Dates and times without a time zone are in the local time zone.
+    fn parse_date(&self, date: &str) -> Option<DateTime<FixedOffset>> {
+        let date = date.trim();
+        let format = match &self.date_format {
+            Some(format) => format,
+            None => {
+                if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+                    return Some(date);
+                }
+                if let Ok(date) = DateTime::parse_from_rfc2822(date) {
+                    return Some(date);
+                }
+                "%Y-%m-%d"
+            }
+        };
+
+        let local = |time: NaiveDateTime| {
+            Local
+                .from_local_datetime(&time)
+                .earliest()
+                .map(DateTime::<FixedOffset>::from)
+        };
+        DateTime::parse_from_str(date, format)
+            .ok()
+            .or_else(|| local(NaiveDateTime::parse_from_str(date, format).ok()?))
+            .or_else(|| {
+                local(
+                    NaiveDate::parse_from_str(date, format)
+                        .ok()?
+                        .and_hms_opt(0, 0, 0)?,
+                )
+            })
+    }
+}
+
+/// What the scraping rules matched for a single post, see [`ScrapeRules::extract`].
+#[derive(Clone, Debug)]
+pub struct ScrapedItem {
+    /// The title of the post
+    pub title: Option<String>,
+
+    /// The url of the post
+    pub link: Option<Url>,
+
+    /// The text of the date of the post, and the date itself if it could be parsed
+    pub date: Option<(String, Option<DateTime<FixedOffset>>)>,
+}
+
+impl ScrapedItem {
+    /// Check whether the post has a title, a link and a valid date, which it needs to be shown.
+    pub fn is_complete(&self) -> bool {
+        self.title.is_some() && self.link.is_some() && matches!(self.date, Some((_, Some(_))))
+    }
+
+    /// Turn the post into a feed item, if it is complete.
+    pub fn into_feed_item(self) -> Option<FeedItem> {
+        let (_, timestamp) = self.date?;
+
+        Some(FeedItem {
+            title: self.title?,
+            id: self.link?.to_string(),
+            timestamp: timestamp?,
+            published: None,
+            summary: None,
+            author: None,
+            categories: vec![],
+            content: None,
+            enclosures: vec![],
+        })
+    }
+}
+
+/// Fetch the page at `url`, sending `credentials` along with the request. Pages can also be read
+/// from local files using `file://` urls.
+pub fn fetch_page(client: &HttpClient, url: &str, credentials: &Credentials) -> Result<String> {
+    if source::is_file_url(url) {
+        return source::read_file(url);
+    }
+
+    let response = client.get_with_credentials(url, credentials)?;
+
+    Ok(response.into_string()?)
+}
+
+/// Get the first element matching `selector`, which is either `element` itself or one of the
+/// elements within it.
+fn select_first<'a>(element: ElementRef<'a>, selector: &Selector) -> Option<ElementRef<'a>> {
+    if selector.matches(&element) {
+        Some(element)
+    } else {
+        element.select(selector).next()
+    }
+}
+
+/// Join the pieces of `text`, collapsing all whitespace into single spaces.
+fn collapse_whitespace<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,295 @@
+//! Serving the items of the subscribed feeds over HTTP: as a combined Atom feed, as JSON and as a
+//! simple web page.
+
+use crate::{
+    error::*,
+    feed::{normalize_tag, Database, Feed, FeedItem},
+    filter::Filter,
+    http::{is_web_url, HttpClient},
+    publish::{combine, to_atom, FeedInfo},
+    window::TimeWindow,
+    xml::escape_xml,
+};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::json;
+use std::{
+    collections::BTreeSet,
+    io::Cursor,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use tiny_http::{Header, Method, Request, Response};
+use url::Url;
+
+/// How often to check whether the server should shut down while waiting for requests.
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The title of the combined feed.
+const TITLE: &str = "gobbler";
+
+/// Serves the items of the feeds in a [`Database`] over HTTP. Feeds are fetched when the first
+/// request comes in, after which they are cached for a while, so that not every request fetches
+/// every feed again.
+pub struct Server<'a> {
+    db: &'a Database,
+    client: &'a HttpClient,
+    filter: &'a Filter,
+
+    /// Serve the items from the last this many weeks
+    weeks: i64,
+
+    /// Serve at most this many items per feed
+    limit: usize,
+
+    /// How long fetched feeds are cached
+    cache_ttl: Duration,
+
+    /// The feeds fetched most recently, and when they were fetched
+    cache: Option<(Instant, Vec<Feed>)>,
+}
+
+impl<'a> Server<'a> {
+    /// Create a new [`Server`] which serves at most `limit` items from the last `weeks` weeks of
+    /// every feed in `db` which matches `filter`, caching the fetched feeds for `cache_ttl`.
+    pub fn new(
+        db: &'a Database,
+        client: &'a HttpClient,
+        filter: &'a Filter,
+        weeks: i64,
+        limit: usize,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            filter,
+            weeks,
+            limit,
+            cache_ttl,
+            cache: None,
+        }
+    }
+
+    /// Serve requests on `address` (such as `127.0.0.1:8080`) until `shutdown` is set.
+    pub fn run(&mut self, address: &str, shutdown: &AtomicBool) -> Result<()> {
+        let server = tiny_http::Server::http(address).map_err(Error::StartServer)?;
+
+        while !shutdown.load(Ordering::SeqCst) {
+            if let Some(request) = server.recv_timeout(SHUTDOWN_CHECK_INTERVAL)? {
+                let response = self.handle(&request);
+
+                // The client may have gone away in the meantime, which is not our problem.
+                let _ = request.respond(response);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a single request.
+    fn handle(&mut self, request: &Request) -> Response<Cursor<Vec<u8>>> {
+        if !matches!(request.method(), Method::Get | Method::Head) {
+            return text_response(405, "Method not allowed");
+        }
+
+        let host = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Host"))
+            .map(|header| header.value.to_string())
+            .unwrap_or_else(|| String::from("localhost"));
+        let Ok(url) = Url::parse(&format!("http://{}{}", host, request.url())) else {
+            return text_response(400, "Bad request");
+        };
+
+        let tag = url
+            .query_pairs()
+            .find(|(key, _)| key == "tag")
+            .map(|(_, tag)| normalize_tag(&tag))
+            .filter(|tag| !tag.is_empty());
+
+        let result = match url.path() {
+            "/" => self
+                .html(tag.as_deref())
+                .map(|html| response(html, "text/html; charset=utf-8")),
+            "/feed.atom" => self
+                .atom(tag.as_deref(), url.as_str())
+                .map(|atom| response(atom, "application/atom+xml; charset=utf-8")),
+            "/items.json" => self
+                .json(tag.as_deref())
+                .map(|json| response(json, "application/json")),
+            _ => return text_response(404, "Not found"),
+        };
+
+        result.unwrap_or_else(|error| text_response(500, &error.to_string()))
+    }
+
+    /// Get the feeds, fetching them again if the cached feeds are too old.
+    fn feeds(&mut self) -> &[Feed] {
+        let stale = self
+            .cache
+            .as_ref()
+            .map(|(fetched_at, _)| fetched_at.elapsed() >= self.cache_ttl)
+            .unwrap_or(true);
+        if stale {
+            let window = TimeWindow::last_weeks(self.weeks);
+            let feeds =
+                self.db
+                    .collect_feeds_with_items_since(self.client, &window, true, self.filter);
+            self.cache = Some((Instant::now(), feeds));
+        }
+
+        self.cache
+            .as_ref()
+            .map(|(_, feeds)| feeds.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Get the items of the feeds tagged with `tag` (or of all feeds), newest first.
+    fn entries(&mut self, tag: Option<&str>) -> Vec<(&Feed, &FeedItem)> {
+        let db = self.db;
+        let limit = self.limit;
+        let feeds = self.feeds().iter().filter(|feed| match tag {
+            Some(tag) => db
+                .subscriptions()
+                .get(feed.name())
+                .map(|subscription| subscription.tags.contains(tag))
+                .unwrap_or(false),
+            None => true,
+        });
+
+        combine(feeds, limit)
+    }
+
+    /// Get the combined Atom feed of the feeds tagged with `tag`, which is served at `self_url`.
+    fn atom(&mut self, tag: Option<&str>, self_url: &str) -> Result<String> {
+        let title = title(tag);
+        // The url depends on how the server is reached, so the id is derived from the tag instead.
+        let id = match tag {
+            Some(tag) => format!(
+                "urn:gobbler:served:{}",
+                utf8_percent_encode(tag, NON_ALPHANUMERIC)
+            ),
+            None => String::from("urn:gobbler:served"),
+        };
+        let info = FeedInfo {
+            title: &title,
+            id: &id,
+            self_url: Some(self_url),
+        };
+
+        to_atom(&info, &self.entries(tag))
+    }
+
+    /// Get the items of the feeds tagged with `tag` as JSON.
+    fn json(&mut self, tag: Option<&str>) -> Result<String> {
+        let items = self
+            .entries(tag)
+            .into_iter()
+            .map(|(feed, item)| feed.export_item(item))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(json!({ "items": items }).to_string())
+    }
+
+    /// Get a web page listing the items of the feeds tagged with `tag`.
+    fn html(&mut self, tag: Option<&str>) -> Result<String> {
+        let query = tag
+            .map(|tag| format!("?tag={}", utf8_percent_encode(tag, NON_ALPHANUMERIC)))
+            .unwrap_or_default();
+        let title = escape_xml(&title(tag));
+
+        let mut html = format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n\
+             <meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title}</title>\n\
+             <link rel=\"alternate\" type=\"application/atom+xml\" title=\"{title}\" \
+               href=\"/feed.atom{query}\">\n\
+             <style>\n\
+             body {{ font-family: sans-serif; max-width: 50em; margin: auto; padding: 1em; }}\n\
+             li {{ margin: 0.5em 0; }}\n\
+             small {{ color: #666; }}\n\
+             </style>\n\
+             </head>\n\
+             <body>\n\
+             <h1>{title}</h1>\n",
+            title = title,
+            query = escape_xml(&query),
+        );
+
+        let tags: BTreeSet<_> = self
+            .db
+            .subscriptions()
+            .values()
+            .flat_map(|subscription| subscription.tags.iter())
+            .collect();
+        if !tags.is_empty() {
+            html.push_str("<nav>Tags: <a href=\"/\">all</a>");
+            for tag in tags {
+                html.push_str(&format!(
+                    " <a href=\"/?tag={}\">{}</a>",
+                    utf8_percent_encode(tag, NON_ALPHANUMERIC),
+                    escape_xml(tag)
+                ));
+            }
+            html.push_str("</nav>\n");
+        }
+
+        html.push_str("<ul>\n");
+        for (feed, item) in self.entries(tag) {
+            // Escaping doesn't keep urls such as `javascript:` urls from running when they are
+            // clicked, so only web urls are linked to, and other urls are shown as text.
+            let url = feed.post_url(item)?;
+            let title = if is_web_url(url.as_str()) {
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_xml(url.as_str()),
+                    escape_xml(&item.title)
+                )
+            } else {
+                format!("{} ({})", escape_xml(&item.title), escape_xml(url.as_str()))
+            };
+            html.push_str(&format!(
+                "<li>{}<br><small>{} · {}</small></li>\n",
+                title,
+                escape_xml(feed.name()),
+                item.timestamp.format("%Y-%m-%d %H:%M"),
+            ));
+        }
+        html.push_str("</ul>\n");
+
+        html.push_str(&format!(
+            "<p><a href=\"/feed.atom{query}\">Atom feed</a> · \
+             <a href=\"/items.json{query}\">JSON</a></p>\n\
+             </body>\n\
+             </html>\n",
+            query = escape_xml(&query),
+        ));
+
+        Ok(html)
+    }
+}
+
+/// Get the title of the combined feed of the feeds tagged with `tag`.
+fn title(tag: Option<&str>) -> String {
+    match tag {
+        Some(tag) => format!("{} ({})", TITLE, tag),
+        None => String::from(TITLE),
+    }
+}
+
+/// Create a response containing `body`, which has the given content type.
+fn response(body: String, content_type: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body).with_header(
+        Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+            .expect("content type is a valid header"),
+    )
+}
+
+/// Create a plain text response with the given status code.
+fn text_response(status: u16, text: &str) -> Response<Cursor<Vec<u8>>> {
+    response(text.to_string(), "text/plain; charset=utf-8").with_status_code(status)
+}
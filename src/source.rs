@@ -0,0 +1,123 @@
+//! Reading feeds from sources other than web servers: local files (`file://` urls) and the output
+//! of commands (`exec:` subscriptions), such as scripts which generate a feed.
+
+use crate::{error::*, hook::shell, watch::format_interval};
+use std::{
+    fs,
+    io::Read,
+    process::Stdio,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+/// The prefix of subscriptions whose feed is printed by a command, such as
+/// `exec:python releases.py`.
+pub const EXEC_PREFIX: &str = "exec:";
+
+/// The time after which a command which prints a feed is stopped.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often to check whether a command has finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait for the output of a command after it finished. Processes started by the
+/// command may keep its output open long after the command itself has finished.
+const OUTPUT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Get the command which prints the feed of `url`, if it is an `exec:` subscription.
+pub fn command_of(url: &str) -> Option<&str> {
+    url.strip_prefix(EXEC_PREFIX)
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+}
+
+/// Check whether the feed at `url` is read from a local file.
+pub fn is_file_url(url: &str) -> bool {
+    url.starts_with("file:")
+}
+
+/// Read the feed in the local file at `url`, such as `file:///C:/builds/releases.atom`.
+pub fn read_file(url: &str) -> Result<String> {
+    let path = Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| Error::InvalidRssFeedUrl(url.to_string()))?;
+
+    fs::read_to_string(&path).map_err(|source| Error::ReadFeedFile { path, source })
+}
+
+/// Run `command` through the shell and return what it prints, which should be a feed. Commands
+/// which fail, or which don't finish within a minute, are reported as errors.
+pub fn run_command(command: &str) -> Result<String> {
+    let failed = |reason: String| Error::FeedCommand {
+        command: command.to_string(),
+        reason,
+    };
+
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| failed(format!("failed to start ({})", error)))?;
+
+    // Read the output on their own threads, so that a command which writes a lot of output can't
+    // block waiting for it to be read.
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        let (sender, receiver) = mpsc::channel();
+        if let Some(mut pipe) = pipe {
+            thread::spawn(move || {
+                let mut output = vec![];
+                let _ = pipe.read_to_end(&mut output);
+                let _ = sender.send(output);
+            });
+        }
+        receiver
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+
+    let deadline = Instant::now() + COMMAND_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(failed(format!(
+                    "timed out after {}",
+                    format_interval(COMMAND_TIMEOUT)
+                )));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(error) => return Err(failed(error.to_string())),
+        }
+    };
+
+    if !status.success() {
+        let mut reason = match status.code() {
+            Some(code) => format!("exited with code {}", code),
+            None => String::from("was terminated"),
+        };
+
+        // The last line of the error output is usually the most relevant.
+        let stderr = stderr.recv_timeout(OUTPUT_GRACE_PERIOD).unwrap_or_default();
+        if let Some(line) = String::from_utf8_lossy(&stderr)
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+        {
+            reason.push_str(": ");
+            reason.push_str(line);
+        }
+
+        return Err(failed(reason));
+    }
+
+    let stdout = stdout
+        .recv_timeout(OUTPUT_GRACE_PERIOD)
+        .map_err(|_| failed(String::from("did not close its output")))?;
+    String::from_utf8(stdout).map_err(|_| failed(String::from("printed invalid UTF-8")))
+}
@@ -0,0 +1,227 @@
+//! Statistics about feeds, based on the posts and fetches stored in the archive, which help to
+//! decide which feeds are worth keeping.
+
+use crate::{archive::FetchHistory, dates::DateFormatter, error::*};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Utc};
+use serde::Serialize;
+use std::io::Write;
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+/// The number of weeks for which the number of posts per week is shown.
+pub const STATS_WEEKS: usize = 12;
+
+/// The number of months for which the number of posts per month is shown.
+pub const STATS_MONTHS: usize = 12;
+
+/// The characters used to draw sparklines, from lowest to highest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Statistics about a feed, or about multiple feeds combined.
+pub struct FeedStats {
+    /// The name of the feed
+    pub name: String,
+
+    /// The number of archived posts
+    pub posts: usize,
+
+    /// The timestamp of the most recent post, if any
+    pub last_post: Option<DateTime<FixedOffset>>,
+
+    /// The average time between two consecutive posts, if there are at least two posts
+    pub average_gap: Option<Duration>,
+
+    /// The number of posts in each of the last [`STATS_WEEKS`] weeks, oldest first
+    pub posts_per_week: Vec<usize>,
+
+    /// The number of posts in each of the last [`STATS_MONTHS`] calendar months, oldest first
+    pub posts_per_month: Vec<usize>,
+
+    /// How often fetching the feed succeeded, and how long it took
+    pub fetches: FetchHistory,
+}
+
+impl FeedStats {
+    /// Compute the statistics of the feed named `name`, from the timestamps of its posts and the
+    /// history of fetching it, as of `now`.
+    pub fn new(
+        name: String,
+        timestamps: &[DateTime<FixedOffset>],
+        fetches: FetchHistory,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut sorted = timestamps.to_vec();
+        sorted.sort();
+
+        let average_gap = match (sorted.first(), sorted.last()) {
+            (Some(first), Some(last)) if sorted.len() > 1 => {
+                Some((*last - *first) / (sorted.len() as i32 - 1))
+            }
+            _ => None,
+        };
+
+        let mut posts_per_week = vec![0; STATS_WEEKS];
+        let mut posts_per_month = vec![0; STATS_MONTHS];
+        for timestamp in &sorted {
+            let weeks_ago = now.signed_duration_since(*timestamp).num_weeks();
+            if (0..STATS_WEEKS as i64).contains(&weeks_ago) {
+                posts_per_week[STATS_WEEKS - 1 - weeks_ago as usize] += 1;
+            }
+
+            let months_ago = month_index(now) - month_index(timestamp.with_timezone(&Utc));
+            if (0..STATS_MONTHS as i32).contains(&months_ago) {
+                posts_per_month[STATS_MONTHS - 1 - months_ago as usize] += 1;
+            }
+        }
+
+        Self {
+            name,
+            posts: sorted.len(),
+            last_post: sorted.last().copied(),
+            average_gap,
+            posts_per_week,
+            posts_per_month,
+            fetches,
+        }
+    }
+
+    /// Writes the statistics to the given [`StandardStream`], formatting dates using `dates`.
+    pub fn print_colored(&self, stdout: &mut StandardStream, dates: &DateFormatter) -> Result<()> {
+        stdout.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Color::Green)))?;
+        writeln!(stdout, "{}:", self.name)?;
+        stdout.reset()?;
+
+        match self.last_post {
+            Some(last_post) => {
+                write!(
+                    stdout,
+                    "    {} post{}, last post: {}",
+                    self.posts,
+                    if self.posts == 1 { "" } else { "s" },
+                    dates.format(last_post)
+                )?;
+                if let Some(gap) = self.average_gap {
+                    write!(stdout, ", one every {} on average", format_gap(gap))?;
+                }
+                writeln!(stdout)?;
+
+                print_sparkline(stdout, "Posts per week ", &self.posts_per_week)?;
+                print_sparkline(stdout, "Posts per month", &self.posts_per_month)?;
+            }
+            None => writeln!(stdout, "    No posts archived yet")?,
+        }
+
+        match (self.fetches.success_rate(), self.fetches.average_latency()) {
+            (Some(rate), latency) => {
+                let color = if rate < 0.5 {
+                    Color::Red
+                } else if rate < 0.9 {
+                    Color::Yellow
+                } else {
+                    Color::Green
+                };
+                write!(stdout, "    Fetched {} times, ", self.fetches.fetches)?;
+                stdout.set_color(ColorSpec::new().set_fg(Some(color)))?;
+                write!(stdout, "{:.0}% successful", rate * 100.0)?;
+                stdout.reset()?;
+                if let Some(latency) = latency {
+                    write!(stdout, ", taking {} ms on average", latency.as_millis())?;
+                }
+                writeln!(stdout)?;
+            }
+            (None, _) => writeln!(stdout, "    Not fetched yet")?,
+        }
+
+        Ok(())
+    }
+
+    /// Convert the statistics to the form in which they are exported as JSON.
+    pub fn to_exported(&self) -> ExportedStats<'_> {
+        ExportedStats {
+            name: &self.name,
+            posts: self.posts,
+            last_post: self.last_post.map(|last_post| last_post.to_rfc3339()),
+            average_gap_in_hours: self.average_gap.map(|gap| gap.num_minutes() as f64 / 60.0),
+            posts_per_week: &self.posts_per_week,
+            posts_per_month: &self.posts_per_month,
+            fetches: self.fetches.fetches,
+            successful_fetches: self.fetches.successes,
+            success_rate: self.fetches.success_rate(),
+            average_latency_in_ms: self
+                .fetches
+                .average_latency()
+                .map(|latency| latency.as_millis() as u64),
+        }
+    }
+}
+
+/// [`FeedStats`] in the form in which they are exported as JSON.
+#[derive(Serialize)]
+pub struct ExportedStats<'a> {
+    /// The name of the feed
+    pub name: &'a str,
+    /// The number of archived posts
+    pub posts: usize,
+    /// The timestamp of the most recent post, in RFC 3339 format
+    pub last_post: Option<String>,
+    /// The average time between two consecutive posts, in hours
+    pub average_gap_in_hours: Option<f64>,
+    /// The number of posts in each of the last weeks, oldest first
+    pub posts_per_week: &'a [usize],
+    /// The number of posts in each of the last calendar months, oldest first
+    pub posts_per_month: &'a [usize],
+    /// The number of times the feed was fetched
+    pub fetches: usize,
+    /// The number of times fetching the feed succeeded
+    pub successful_fetches: usize,
+    /// The fraction of the fetches which succeeded
+    pub success_rate: Option<f64>,
+    /// The average time a successful fetch took, in milliseconds
+    pub average_latency_in_ms: Option<u64>,
+}
+
+/// Writes a labeled sparkline of `counts`, followed by the highest count.
+fn print_sparkline(stdout: &mut StandardStream, label: &str, counts: &[usize]) -> Result<()> {
+    write!(stdout, "    {} ", label)?;
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue)))?;
+    write!(stdout, "{}", sparkline(counts))?;
+    stdout.reset()?;
+    writeln!(
+        stdout,
+        " (at most {})",
+        counts.iter().max().copied().unwrap_or(0)
+    )?;
+
+    Ok(())
+}
+
+/// Draw `counts` as a sparkline, with one character per count. Zero is drawn as a space, so that
+/// periods without posts stand out.
+pub fn sparkline(counts: &[usize]) -> String {
+    let max = counts.iter().max().copied().unwrap_or(0);
+    counts
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                ' '
+            } else {
+                SPARKS[(count * SPARKS.len()).div_ceil(max) - 1]
+            }
+        })
+        .collect()
+}
+
+/// Get the number of the calendar month `timestamp` falls in, counting from year 0.
+fn month_index(timestamp: DateTime<Utc>) -> i32 {
+    timestamp.year() * 12 + timestamp.month0() as i32
+}
+
+/// Format `gap`, the time between two posts, such as `3.5 days` or `6 hours`.
+fn format_gap(gap: Duration) -> String {
+    if gap >= Duration::days(2) {
+        format!("{:.1} days", gap.num_minutes() as f64 / (60.0 * 24.0))
+    } else if gap >= Duration::hours(2) {
+        format!("{} hours", gap.num_hours())
+    } else {
+        format!("{} minutes", gap.num_minutes().max(1))
+    }
+}
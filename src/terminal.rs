@@ -0,0 +1,129 @@
+//! Adapting the output to the terminal it is shown in: clickable links, shortened urls and
+//! truncating lines to the width of the terminal.
+
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use url::Url;
+
+/// The maximum width of a shortened url, in columns of the terminal.
+const SHORT_URL_LENGTH: usize = 40;
+
+/// Values of `TERM_PROGRAM` set by terminals which support hyperlinks.
+const HYPERLINK_TERM_PROGRAMS: &[&str] = &["ghostty", "Hyper", "iTerm.app", "vscode", "WezTerm"];
+
+/// Parts of `TERM` set by terminals which support hyperlinks.
+const HYPERLINK_TERMS: &[&str] = &["alacritty", "foot", "ghostty", "kitty", "wezterm"];
+
+/// The first version of VTE (used by GNOME Terminal and others) which supports hyperlinks.
+const MIN_VTE_VERSION: u32 = 5000;
+
+/// Whether posts are shown as clickable links.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HyperlinkMode {
+    /// Only when the terminal is known to support them
+    Auto,
+    /// Always, even when the terminal may not support them
+    Always,
+    /// Never
+    Never,
+}
+
+impl HyperlinkMode {
+    /// Check whether hyperlinks should be used.
+    pub fn enabled(self) -> bool {
+        match self {
+            HyperlinkMode::Auto => supports_hyperlinks(),
+            HyperlinkMode::Always => true,
+            HyperlinkMode::Never => false,
+        }
+    }
+}
+
+/// How the urls of posts are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum UrlStyle {
+    /// The full url
+    Full,
+    /// The host and the start of the path
+    Short,
+    /// No url, the title links to the post instead. Falls back to the full url when hyperlinks
+    /// are not used
+    Hidden,
+}
+
+/// Check whether standard output is a terminal which supports OSC 8 hyperlinks. As terminals
+/// can't be asked whether they do, this is based on the environment variables they set.
+pub fn supports_hyperlinks() -> bool {
+    if !io::stdout().is_terminal() {
+        return false;
+    }
+
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    if ["WT_SESSION", "KONSOLE_VERSION", "DOMTERM"]
+        .iter()
+        .any(|&name| !var(name).is_empty())
+    {
+        return true;
+    }
+
+    if var("VTE_VERSION")
+        .parse::<u32>()
+        .map(|version| version >= MIN_VTE_VERSION)
+        .unwrap_or(false)
+    {
+        return true;
+    }
+
+    let term = var("TERM");
+    HYPERLINK_TERM_PROGRAMS.contains(&var("TERM_PROGRAM").as_str())
+        || HYPERLINK_TERMS.iter().any(|name| term.contains(name))
+}
+
+/// Get the width of the terminal, if standard output is a terminal.
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+
+    crossterm::terminal::size()
+        .ok()
+        .map(|(columns, _)| usize::from(columns))
+        .filter(|columns| *columns > 0)
+}
+
+/// Make `text` a hyperlink to `url`, using the OSC 8 escape sequence.
+pub fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Shorten `url` to its host and as much of its path as fits.
+pub fn shorten_url(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let path = url.path().trim_end_matches('/');
+
+    truncate(&format!("{}{}", host, path), SHORT_URL_LENGTH)
+}
+
+/// Truncate `text` to at most `width` columns of the terminal, ending it with an ellipsis if it was
+/// cut off. Characters such as CJK characters and emoji take up two columns.
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    // Leave room for the ellipsis, which takes up a single column.
+    let mut truncated = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        used += c.width().unwrap_or(0);
+        if used > width.saturating_sub(1) {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push('…');
+
+    truncated
+}
@@ -0,0 +1,652 @@
+//! A full-screen terminal interface for reading feeds, with a list of feeds, a list of the posts of
+//! the selected feed and a preview of the selected post.
+
+use crate::{
+    browser::Opener,
+    dates::DateFormatter,
+    error::*,
+    feed::{Database, Feed, FeedItem},
+    filter::{Filter, ItemField, ItemFilter},
+    http::HttpClient,
+    marks::Marks,
+    window::TimeWindow,
+};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use indicatif::ParallelProgressIterator;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Frame, Terminal,
+};
+use rayon::prelude::*;
+use std::io;
+
+/// The number of lines the preview is scrolled by at once.
+const PREVIEW_SCROLL_LINES: u16 = 10;
+
+/// The keys which can be used, shown when there is nothing else to show in the status line.
+const HELP: &str =
+    "q quit · Tab switch · o open · r read · s star · u unread · / filter · R refresh · Space scroll";
+
+/// A full-screen terminal interface for reading the feeds in a [`Database`].
+pub struct Tui<'a> {
+    db: &'a Database,
+    client: &'a HttpClient,
+    filter: &'a Filter,
+    opener: &'a Opener,
+
+    /// Formats the timestamps of the posts
+    dates: &'a DateFormatter,
+
+    /// Show the posts within this window
+    window: TimeWindow,
+
+    /// The posts which have been read or starred
+    marks: &'a mut Marks,
+
+    /// The feeds, in the order in which they are listed
+    feeds: Vec<LoadedFeed>,
+
+    /// The pane which has the focus
+    focus: Focus,
+
+    /// The selected feed
+    feed_list: ListState,
+
+    /// The selected post, as an index into the posts which are shown
+    item_list: ListState,
+
+    /// The number of lines the preview is scrolled down
+    preview_scroll: u16,
+
+    /// Only show the posts which match this filter, if set
+    search: Option<ItemFilter>,
+
+    /// The filter which is being typed, if any
+    input: Option<String>,
+
+    /// Only show the posts which have not been read yet
+    unread_only: bool,
+
+    /// The message shown in the status line
+    status: String,
+
+    /// Whether the selected feed should be fetched again
+    refresh: bool,
+
+    /// Whether the interface should be closed
+    quit: bool,
+}
+
+/// A feed as fetched when the interface was opened, or when it was last refreshed.
+struct LoadedFeed {
+    /// The name of the feed
+    name: String,
+
+    /// The feed, or the reason it could not be fetched
+    feed: std::result::Result<Feed, String>,
+}
+
+/// The panes which can have the focus.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Feeds,
+    Items,
+}
+
+/// Restores the terminal when it goes out of scope, even when `gobbler` panics.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+impl<'a> Tui<'a> {
+    /// Create a new [`Tui`] which shows the posts within `window` of every feed in `db` which
+    /// matches `filter`, keeping track of the posts which have been read or starred in `marks`.
+    /// Posts are opened using `opener`, and their timestamps are formatted using `dates`.
+    pub fn new(
+        db: &'a Database,
+        client: &'a HttpClient,
+        filter: &'a Filter,
+        opener: &'a Opener,
+        dates: &'a DateFormatter,
+        window: TimeWindow,
+        marks: &'a mut Marks,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            filter,
+            opener,
+            dates,
+            window,
+            marks,
+            feeds: vec![],
+            focus: Focus::Feeds,
+            feed_list: ListState::default(),
+            item_list: ListState::default(),
+            preview_scroll: 0,
+            search: None,
+            input: None,
+            unread_only: false,
+            status: String::new(),
+            refresh: false,
+            quit: false,
+        }
+    }
+
+    /// Fetch the feeds and show the interface until it is closed.
+    pub fn run(&mut self) -> Result<()> {
+        self.fetch_feeds();
+        self.feed_list
+            .select(if self.feeds.is_empty() { None } else { Some(0) });
+        self.select_first_item();
+
+        enable_raw_mode().map_err(Error::Terminal)?;
+        let _guard = TerminalGuard;
+        execute!(io::stdout(), EnterAlternateScreen).map_err(Error::Terminal)?;
+        let mut terminal =
+            Terminal::new(CrosstermBackend::new(io::stdout())).map_err(Error::Terminal)?;
+
+        while !self.quit {
+            self.clamp_item_selection();
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(Error::Terminal)?;
+
+            // Refresh after drawing, so that the status line shows what is going on.
+            if self.refresh {
+                self.refresh = false;
+                self.refresh_selected_feed();
+                continue;
+            }
+
+            if let Event::Key(key) = event::read().map_err(Error::Terminal)? {
+                // Windows also reports key releases, which should be ignored.
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch every feed which matches the filter.
+    fn fetch_feeds(&mut self) {
+        let (db, client, filter, window) = (self.db, self.client, self.filter, &self.window);
+        let names: Vec<&String> = db
+            .subscriptions()
+            .iter()
+            .filter(|(name, subscription)| filter.matches(name, subscription))
+            .map(|(name, _)| name)
+            .collect();
+
+        self.feeds = names
+            .par_iter()
+            .progress_count(names.len() as u64)
+            .map(|name| LoadedFeed {
+                name: name.to_string(),
+                feed: db
+                    .fetch_feed_since(client, name, window, filter)
+                    .map_err(|error| error.to_string()),
+            })
+            .collect();
+    }
+
+    /// Fetch the selected feed again.
+    fn refresh_selected_feed(&mut self) {
+        let Some(loaded) = self
+            .feed_list
+            .selected()
+            .and_then(|index| self.feeds.get_mut(index))
+        else {
+            return;
+        };
+
+        loaded.feed = self
+            .db
+            .fetch_feed_since(self.client, &loaded.name, &self.window, self.filter)
+            .map_err(|error| error.to_string());
+        self.status = match &loaded.feed {
+            Ok(_) => format!("Refreshed '{}'", loaded.name),
+            Err(error) => format!("Failed to refresh '{}': {}", loaded.name, error),
+        };
+        self.select_first_item();
+    }
+
+    /// Get the indices of the posts of `feed` which are shown.
+    fn visible_items(&self, feed: &Feed) -> Vec<usize> {
+        feed.items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                self.search
+                    .as_ref()
+                    .map(|search| search.matches(item))
+                    .unwrap_or(true)
+            })
+            .filter(|(_, item)| !self.unread_only || !self.marks.is_read(feed.name(), item))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Get the selected feed, if it could be fetched.
+    fn selected_feed(&self) -> Option<&Feed> {
+        self.feed_list
+            .selected()
+            .and_then(|index| self.feeds.get(index))
+            .and_then(|loaded| loaded.feed.as_ref().ok())
+    }
+
+    /// Get the index of the selected feed, and the index of the selected post within that feed.
+    fn selection(&self) -> Option<(usize, usize)> {
+        let feed_index = self.feed_list.selected()?;
+        let feed = self.selected_feed()?;
+        let item_index = *self.visible_items(feed).get(self.item_list.selected()?)?;
+
+        Some((feed_index, item_index))
+    }
+
+    /// Get the selected post, and the feed it belongs to.
+    fn selected_item(&self) -> Option<(&Feed, &FeedItem)> {
+        let (feed_index, item_index) = self.selection()?;
+        let feed = self.feeds[feed_index].feed.as_ref().ok()?;
+
+        Some((feed, &feed.items[item_index]))
+    }
+
+    /// Select the first post of the selected feed, if there is one.
+    fn select_first_item(&mut self) {
+        let has_items = self
+            .selected_feed()
+            .map(|feed| !self.visible_items(feed).is_empty())
+            .unwrap_or(false);
+        self.item_list
+            .select(if has_items { Some(0) } else { None });
+        self.preview_scroll = 0;
+    }
+
+    /// Make sure the selected post is one of the posts which are shown, as posts can disappear
+    /// when they are marked as read or filtered out.
+    fn clamp_item_selection(&mut self) {
+        let count = self
+            .selected_feed()
+            .map(|feed| self.visible_items(feed).len())
+            .unwrap_or(0);
+        let selected = match (count, self.item_list.selected()) {
+            (0, _) => None,
+            (_, Some(selected)) => Some(selected.min(count - 1)),
+            (_, None) => Some(0),
+        };
+        self.item_list.select(selected);
+    }
+
+    /// Handle a key press.
+    fn handle_key(&mut self, key: KeyEvent) {
+        if let Some(input) = &mut self.input {
+            match key.code {
+                KeyCode::Enter => {
+                    let input = self.input.take().unwrap_or_default();
+                    self.apply_search(&input);
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        self.status.clear();
+        let result = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+                Ok(())
+            }
+            KeyCode::Esc if self.search.is_some() => {
+                self.apply_search("");
+                Ok(())
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.quit = true;
+                Ok(())
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Feeds => Focus::Items,
+                    Focus::Items => Focus::Feeds,
+                };
+                Ok(())
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.focus = Focus::Feeds;
+                Ok(())
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.focus = Focus::Items;
+                Ok(())
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.move_selection(1);
+                Ok(())
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.move_selection(-1);
+                Ok(())
+            }
+            KeyCode::Home | KeyCode::Char('g') => {
+                self.move_selection(isize::MIN);
+                Ok(())
+            }
+            KeyCode::End | KeyCode::Char('G') => {
+                self.move_selection(isize::MAX);
+                Ok(())
+            }
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                self.preview_scroll = self.preview_scroll.saturating_add(PREVIEW_SCROLL_LINES);
+                Ok(())
+            }
+            KeyCode::PageUp | KeyCode::Char('b') => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(PREVIEW_SCROLL_LINES);
+                Ok(())
+            }
+            KeyCode::Enter | KeyCode::Char('o') => self.open_selected_item(),
+            KeyCode::Char('r') => self.toggle_read(),
+            KeyCode::Char('s') => self.toggle_starred(),
+            KeyCode::Char('u') => {
+                self.unread_only = !self.unread_only;
+                self.select_first_item();
+                Ok(())
+            }
+            KeyCode::Char('/') => {
+                self.input = Some(String::new());
+                Ok(())
+            }
+            KeyCode::Char('R') | KeyCode::F(5) => {
+                if let Some(index) = self.feed_list.selected() {
+                    self.status = format!("Refreshing '{}'…", self.feeds[index].name);
+                    self.refresh = true;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+
+        if let Err(error) = result {
+            self.status = error.to_string();
+        }
+    }
+
+    /// Move the selection in the focused pane by `offset`, staying within the list.
+    fn move_selection(&mut self, offset: isize) {
+        let (list, count) = match self.focus {
+            Focus::Feeds => (&mut self.feed_list, self.feeds.len()),
+            Focus::Items => {
+                let count = self
+                    .selected_feed()
+                    .map(|feed| self.visible_items(feed).len())
+                    .unwrap_or(0);
+                (&mut self.item_list, count)
+            }
+        };
+        if count == 0 {
+            return;
+        }
+
+        let selected = list
+            .selected()
+            .unwrap_or(0)
+            .saturating_add_signed(offset)
+            .min(count - 1);
+        list.select(Some(selected));
+
+        match self.focus {
+            Focus::Feeds => self.select_first_item(),
+            Focus::Items => self.preview_scroll = 0,
+        }
+    }
+
+    /// Only show the posts matching `input`, or all posts if `input` is empty.
+    fn apply_search(&mut self, input: &str) {
+        if input.is_empty() {
+            self.search = None;
+        } else {
+            let fields = vec![ItemField::Title, ItemField::Summary, ItemField::Author];
+            match ItemFilter::new(Some(input), None, fields) {
+                Ok(search) => {
+                    self.search = Some(search);
+                    self.status = format!("Showing posts matching '{}', Esc shows all", input);
+                }
+                Err(error) => self.status = error.to_string(),
+            }
+        }
+
+        self.select_first_item();
+    }
+
+    /// Open the selected post in the web browser, marking it as read.
+    fn open_selected_item(&mut self) -> Result<()> {
+        let Some((feed_index, item_index)) = self.selection() else {
+            return Ok(());
+        };
+        let Ok(feed) = &self.feeds[feed_index].feed else {
+            return Ok(());
+        };
+        let item = &feed.items[item_index];
+
+        self.opener.open(feed.post_url(item)?.as_str())?;
+        self.marks.set_read(feed, item, true)
+    }
+
+    /// Mark the selected post as read if it is unread, or as unread if it is read.
+    fn toggle_read(&mut self) -> Result<()> {
+        let Some((feed_index, item_index)) = self.selection() else {
+            return Ok(());
+        };
+        let Ok(feed) = &self.feeds[feed_index].feed else {
+            return Ok(());
+        };
+        let item = &feed.items[item_index];
+
+        let read = !self.marks.is_read(feed.name(), item);
+        self.marks.set_read(feed, item, read)
+    }
+
+    /// Star the selected post if it is not starred, or unstar it if it is.
+    fn toggle_starred(&mut self) -> Result<()> {
+        let Some((feed_index, item_index)) = self.selection() else {
+            return Ok(());
+        };
+        let Ok(feed) = &self.feeds[feed_index].feed else {
+            return Ok(());
+        };
+        let item = &feed.items[item_index];
+
+        let starred = !self.marks.is_starred(feed.name(), item);
+        self.marks.set_starred(feed, item, starred)
+    }
+
+    /// Draw the interface.
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).split(frame.size());
+        let columns = Layout::horizontal([Constraint::Percentage(25), Constraint::Percentage(75)])
+            .split(rows[0]);
+        let panes = Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(columns[1]);
+
+        let feeds = List::new(self.feed_lines())
+            .block(self.block(" Feeds ", Focus::Feeds))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(feeds, columns[0], &mut self.feed_list);
+
+        let items = List::new(self.item_lines())
+            .block(self.block(" Posts ", Focus::Items))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(items, panes[0], &mut self.item_list);
+
+        let preview = Paragraph::new(self.preview_lines())
+            .block(Block::default().borders(Borders::ALL).title(" Preview "))
+            .wrap(Wrap { trim: false })
+            .scroll((self.preview_scroll, 0));
+        frame.render_widget(preview, panes[1]);
+
+        let status = match &self.input {
+            Some(input) => Line::from(format!("/{}", input)),
+            None if !self.status.is_empty() => Line::from(self.status.as_str()),
+            None => Line::styled(HELP, Style::default().fg(Color::DarkGray)),
+        };
+        frame.render_widget(Paragraph::new(status), rows[1]);
+    }
+
+    /// Create the block surrounding the pane `pane`, highlighting it if it has the focus.
+    fn block(&self, title: &'static str, pane: Focus) -> Block<'static> {
+        let block = Block::default().borders(Borders::ALL).title(title);
+        if self.focus == pane {
+            block.border_style(Style::default().fg(Color::Cyan))
+        } else {
+            block
+        }
+    }
+
+    /// Get the lines of the list of feeds, which show the number of unread posts of every feed.
+    fn feed_lines(&self) -> Vec<ListItem<'static>> {
+        self.feeds
+            .iter()
+            .map(|loaded| match &loaded.feed {
+                Ok(feed) => {
+                    let unread = self
+                        .visible_items(feed)
+                        .into_iter()
+                        .filter(|index| !self.marks.is_read(feed.name(), &feed.items[*index]))
+                        .count();
+                    if unread == 0 {
+                        ListItem::new(loaded.name.clone())
+                    } else {
+                        ListItem::new(Line::from(vec![
+                            Span::styled(
+                                loaded.name.clone(),
+                                Style::default().add_modifier(Modifier::BOLD),
+                            ),
+                            Span::raw(format!(" ({})", unread)),
+                        ]))
+                    }
+                }
+                Err(_) => ListItem::new(Line::styled(
+                    format!("{} (failed)", loaded.name),
+                    Style::default().fg(Color::Red),
+                )),
+            })
+            .collect()
+    }
+
+    /// Get the lines of the list of posts of the selected feed. Unread posts are shown in bold.
+    fn item_lines(&self) -> Vec<ListItem<'static>> {
+        let Some(feed) = self.selected_feed() else {
+            return vec![];
+        };
+
+        self.visible_items(feed)
+            .into_iter()
+            .map(|index| {
+                let item = &feed.items[index];
+                let star = if self.marks.is_starred(feed.name(), item) {
+                    "★ "
+                } else {
+                    "  "
+                };
+                let title_style = if self.marks.is_read(feed.name(), item) {
+                    Style::default()
+                } else {
+                    Style::default().add_modifier(Modifier::BOLD)
+                };
+
+                ListItem::new(Line::from(vec![
+                    Span::styled(star, Style::default().fg(Color::Yellow)),
+                    // Only the day fits in the list, the preview shows the entire timestamp.
+                    Span::styled(
+                        format!("{} ", self.dates.day_of(item.timestamp).format("%Y-%m-%d")),
+                        Style::default().fg(Color::Blue),
+                    ),
+                    Span::styled(item.title.clone(), title_style),
+                ]))
+            })
+            .collect()
+    }
+
+    /// Get the lines of the preview of the selected post.
+    fn preview_lines(&self) -> Vec<Line<'static>> {
+        if let Some(Err(error)) = self
+            .feed_list
+            .selected()
+            .and_then(|index| self.feeds.get(index))
+            .map(|loaded| &loaded.feed)
+        {
+            return vec![
+                Line::styled(
+                    format!("Failed to fetch this feed: {}", error),
+                    Style::default().fg(Color::Red),
+                ),
+                Line::from("Press R to try again."),
+            ];
+        }
+
+        let Some((feed, item)) = self.selected_item() else {
+            return vec![Line::from(format!("No posts {}", self.window))];
+        };
+
+        let mut byline = vec![feed.name().to_string(), self.dates.format(item.timestamp)];
+        if let Some(author) = &item.author {
+            byline.push(format!("by {}", author));
+        }
+
+        let mut lines = vec![
+            Line::styled(
+                item.title.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Line::styled(byline.join(" · "), Style::default().fg(Color::Blue)),
+        ];
+        if !item.categories.is_empty() {
+            lines.push(Line::styled(
+                item.categories.join(", "),
+                Style::default().add_modifier(Modifier::ITALIC),
+            ));
+        }
+        if let Ok(url) = feed.post_url(item) {
+            lines.push(Line::styled(
+                url.to_string(),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        for enclosure in &item.enclosures {
+            lines.push(Line::from(format!("Enclosure: {}", enclosure)));
+        }
+        lines.push(Line::default());
+
+        let text = item.content.as_ref().or(item.summary.as_ref());
+        match text {
+            Some(text) => lines.extend(text.lines().map(|line| Line::from(line.to_string()))),
+            None => lines.push(Line::styled(
+                "This post has no text, press o to open it in the browser.",
+                Style::default().fg(Color::DarkGray),
+            )),
+        }
+
+        lines
+    }
+}
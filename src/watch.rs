@@ -0,0 +1,339 @@
+//! Watching feeds for new items, polling each feed on its own schedule.
+
+use crate::{
+    error::*,
+    feed::{Database, Feed},
+    filter::Filter,
+    http::HttpClient,
+};
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The longest interval a feed can ask to be polled at. Some feeds claim to update yearly, which
+/// would mean they are effectively never polled.
+const MAX_HINTED_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The longest time to wait before polling a feed again after polling it failed, unless the
+/// feed is polled less often than this anyway.
+const MAX_BACKOFF: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How often to check whether the watcher should shut down while waiting for the next poll.
+const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Something which happened while watching feeds.
+pub enum WatchEvent {
+    /// New items appeared in a feed, the feed only contains the new items
+    NewItems(Feed),
+
+    /// Polling a feed failed, it is polled again after `retry_in`
+    PollFailed {
+        /// The name of the feed
+        name: String,
+        /// The reason polling the feed failed
+        error: Error,
+        /// The time until the feed is polled again
+        retry_in: Duration,
+    },
+}
+
+/// Watches feeds for new items. Every feed is polled at the interval of the watcher, unless the
+/// subscription sets its own interval, or the feed asks to be polled less often using a `ttl` or
+/// `sy:updatePeriod` element. Feeds which fail to be polled are polled less and less often, until
+/// they are polled successfully again.
+pub struct Watcher {
+    /// The interval at which feeds are polled by default
+    interval: Duration,
+
+    /// The state of every feed which has been polled, by name
+    feeds: BTreeMap<String, FeedState>,
+}
+
+/// The state of a single feed being watched.
+struct FeedState {
+    /// The next time the feed should be polled
+    next_poll: Instant,
+
+    /// The ids of the items in the feed when it was last polled successfully, if it has been
+    seen: Option<HashSet<String>>,
+
+    /// The number of times in a row polling the feed failed
+    failures: u32,
+
+    /// The interval at which the feed asked to be polled when it was last polled successfully
+    ttl: Option<Duration>,
+}
+
+impl Watcher {
+    /// Create a new [`Watcher`] which polls feeds every `interval` by default.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            feeds: BTreeMap::new(),
+        }
+    }
+
+    /// Watch the feeds in `db` which match `filter` until `shutdown` is set, passing every
+    /// [`WatchEvent`] to `handle`. The first poll of a feed only records which items it contains,
+    /// after that only items which newly appear (and which match `filter`) are reported.
+    pub fn run<F>(
+        &mut self,
+        client: &HttpClient,
+        db: &Database,
+        filter: &Filter,
+        shutdown: &AtomicBool,
+        mut handle: F,
+    ) -> Result<()>
+    where
+        F: FnMut(WatchEvent) -> Result<()>,
+    {
+        while !shutdown.load(Ordering::SeqCst) {
+            for event in self.poll_due_feeds(client, db, filter) {
+                handle(event)?;
+            }
+
+            let next_poll = self
+                .feeds
+                .values()
+                .map(|state| state.next_poll)
+                .min()
+                .unwrap_or_else(|| Instant::now() + self.interval);
+            while !shutdown.load(Ordering::SeqCst) {
+                let now = Instant::now();
+                if now >= next_poll {
+                    break;
+                }
+
+                thread::sleep((next_poll - now).min(SHUTDOWN_CHECK_INTERVAL));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Poll all feeds which are due to be polled, returning what happened.
+    fn poll_due_feeds(
+        &mut self,
+        client: &HttpClient,
+        db: &Database,
+        filter: &Filter,
+    ) -> Vec<WatchEvent> {
+        let now = Instant::now();
+        let due: Vec<_> = db
+            .subscriptions()
+            .iter()
+            .filter(|(name, subscription)| filter.matches(name, subscription))
+            .filter(|(name, _)| {
+                self.feeds
+                    .get(*name)
+                    .map(|state| state.next_poll <= now)
+                    .unwrap_or(true)
+            })
+            .collect();
+
+        let results: Vec<_> = due
+            .into_par_iter()
+            .map(|(name, subscription)| {
+                let result = filter
+                    .item_filter_for(subscription)
+                    .and_then(|item_filter| {
+                        let credentials = db.credentials(subscription)?;
+                        let started = Instant::now();
+                        let result = subscription.fetch(client, &credentials);
+                        db.record_fetch(name, result.is_ok(), started.elapsed());
+
+                        let (items, ttl) = result?;
+                        let mut feed = Feed::new(name.clone(), subscription.url.clone(), items)?;
+                        db.archive(&mut feed);
+
+                        let ids: HashSet<_> =
+                            feed.items.iter().map(|item| item.id.clone()).collect();
+                        let items = feed
+                            .items
+                            .into_iter()
+                            .filter(|item| item_filter.matches(item))
+                            .collect::<Vec<_>>();
+
+                        Ok((items, ids, ttl))
+                    });
+
+                (name, subscription, result)
+            })
+            .collect();
+
+        let mut events = vec![];
+        for (name, subscription, result) in results {
+            // Explicitly configured intervals take precedence over whatever the feed asks for.
+            let configured = subscription
+                .interval
+                .as_deref()
+                .and_then(|interval| parse_interval(interval).ok());
+
+            match result {
+                Ok((items, ids, ttl)) => {
+                    let ttl = ttl.map(|ttl| ttl.min(MAX_HINTED_INTERVAL));
+                    let interval =
+                        configured.unwrap_or_else(|| ttl.unwrap_or_default().max(self.interval));
+
+                    let seen = self.feeds.get(name).and_then(|state| state.seen.as_ref());
+                    let new_items: Vec<_> = match seen {
+                        Some(seen) => items
+                            .into_iter()
+                            .filter(|item| !seen.contains(&item.id))
+                            .collect(),
+                        None => vec![],
+                    };
+
+                    self.feeds.insert(
+                        name.clone(),
+                        FeedState {
+                            next_poll: now + interval,
+                            seen: Some(ids),
+                            failures: 0,
+                            ttl,
+                        },
+                    );
+
+                    if !new_items.is_empty() {
+                        match Feed::new(name.clone(), subscription.url.clone(), new_items) {
+                            Ok(feed) => events.push(WatchEvent::NewItems(feed)),
+                            Err(error) => events.push(WatchEvent::PollFailed {
+                                name: name.clone(),
+                                error,
+                                retry_in: interval,
+                            }),
+                        }
+                    }
+                }
+                Err(error) => {
+                    let state = self.feeds.entry(name.clone()).or_insert(FeedState {
+                        next_poll: now,
+                        seen: None,
+                        failures: 0,
+                        ttl: None,
+                    });
+                    state.failures += 1;
+
+                    let interval = configured
+                        .unwrap_or_else(|| state.ttl.unwrap_or_default().max(self.interval));
+                    let retry_in = backoff(interval, state.failures);
+                    state.next_poll = now + retry_in;
+
+                    events.push(WatchEvent::PollFailed {
+                        name: name.clone(),
+                        error,
+                        retry_in,
+                    });
+                }
+            }
+        }
+
+        events
+    }
+}
+
+/// Get the time to wait before polling a feed which is normally polled every `interval` again,
+/// after polling it failed `failures` times in a row. The time doubles with every failure.
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    let backoff = interval.saturating_mul(1 << failures.min(16));
+    backoff.min(MAX_BACKOFF).max(interval)
+}
+
+/// Parse an interval such as `30s`, `15m`, `2h` or `1d`.
+pub fn parse_interval(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let invalid = || Error::InvalidInterval(input.to_string());
+
+    let unit = input.chars().last().ok_or_else(invalid)?;
+    let amount = input[..input.len() - unit.len_utf8()]
+        .parse::<u64>()
+        .map_err(|_| invalid())?;
+    let unit_length = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 60 * 60 * 24,
+        _ => return Err(invalid()),
+    };
+    let seconds = amount.checked_mul(unit_length).ok_or_else(invalid)?;
+
+    if seconds == 0 {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format `interval` in the largest unit which represents it exactly, such as `15m` or `2h`.
+pub fn format_interval(interval: Duration) -> String {
+    let seconds = interval.as_secs();
+    for (unit, length) in [("d", 60 * 60 * 24), ("h", 60 * 60), ("m", 60)] {
+        if seconds >= length && seconds.checked_rem(length) == Some(0) {
+            return format!("{}{}", seconds / length, unit);
+        }
+    }
+
+    format!("{}s", seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(15 * 60));
+        assert_eq!(
+            parse_interval(" 2h ").unwrap(),
+            Duration::from_secs(2 * 60 * 60)
+        );
+        assert_eq!(
+            parse_interval("1d").unwrap(),
+            Duration::from_secs(24 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_intervals() {
+        for input in [
+            "",
+            "m",
+            "15",
+            "0m",
+            "-5m",
+            "5w",
+            "5é",
+            "é",
+            "99999999999999999d",
+        ] {
+            assert!(
+                matches!(parse_interval(input), Err(Error::InvalidInterval(_))),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn formats_intervals() {
+        assert_eq!(format_interval(Duration::from_secs(90)), "90s");
+        assert_eq!(format_interval(Duration::from_secs(15 * 60)), "15m");
+        assert_eq!(format_interval(Duration::from_secs(2 * 60 * 60)), "2h");
+        assert_eq!(format_interval(Duration::from_secs(24 * 60 * 60)), "1d");
+    }
+
+    #[test]
+    fn backs_off() {
+        let interval = Duration::from_secs(15 * 60);
+        assert_eq!(backoff(interval, 0), interval);
+        assert_eq!(backoff(interval, 1), interval * 2);
+        assert_eq!(backoff(interval, 40), MAX_BACKOFF);
+
+        let interval = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(backoff(interval, 3), interval);
+    }
+}
@@ -0,0 +1,806 @@
+//! Delivering new feed items to webhooks, such as those of Slack or Discord channels.
+
+use crate::{
+    error::*,
+    feed::{parse_tags, Feed, FeedItem, Subscription},
+    http::HttpClient,
+    lines::{append_lines, escape, read_lines, read_log, write_lines},
+};
+use chrono::Local;
+use clap::ValueEnum;
+use serde_json::json;
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+use url::Url;
+
+/// The file used to store webhooks, which is stored next to the subscriptions file.
+pub const WEBHOOKS_FILE: &str = "webhooks.db";
+
+/// The file used to keep track of which items have been delivered to which webhooks.
+const DELIVERY_LOG_FILE: &str = "webhooks.log";
+
+/// The number of deliveries which are remembered for every webhook and feed. Feeds contain far
+/// fewer items than this, so older deliveries are of items which have long left their feed.
+const DELIVERIES_PER_FEED: usize = 1000;
+
+/// The number of times delivering a payload is attempted before giving up.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// The time to wait before retrying a failed delivery for the first time. This doubles with every
+/// attempt.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest time to wait before retrying a delivery when the server asks to wait.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// The maximum length of a Slack message. Slack truncates longer messages.
+const SLACK_MESSAGE_LENGTH: usize = 3000;
+
+/// The maximum length of a Discord message. Discord rejects longer messages.
+const DISCORD_MESSAGE_LENGTH: usize = 2000;
+
+/// The shape of the payload posted to a webhook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PayloadFormat {
+    /// A JSON object containing the name of the feed and its new items
+    Generic,
+    /// A message for a Slack incoming webhook
+    Slack,
+    /// A message for a Discord webhook
+    Discord,
+}
+
+impl fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A webhook to which new items are posted.
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    /// The url the items are posted to
+    pub url: String,
+
+    /// The shape of the payload
+    pub format: PayloadFormat,
+
+    /// Only post items from feeds with one of these names, if any are given
+    pub feeds: BTreeSet<String>,
+
+    /// Only post items from feeds with one of these tags, if any are given
+    pub tags: BTreeSet<String>,
+}
+
+impl Webhook {
+    /// Create a new [`Webhook`] which posts the items of every feed to `url`.
+    pub fn new(url: String, format: PayloadFormat) -> Self {
+        Self {
+            url,
+            format,
+            feeds: BTreeSet::new(),
+            tags: BTreeSet::new(),
+        }
+    }
+
+    /// Check whether items of the feed with the given name and subscription are posted to this
+    /// webhook.
+    pub fn accepts(&self, name: &str, subscription: &Subscription) -> bool {
+        (self.feeds.is_empty() || self.feeds.contains(name))
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| subscription.tags.contains(tag)))
+    }
+
+    /// Parse a line from the webhooks file, returning the name of the webhook and the webhook
+    /// itself. Lines have the same form as those in the subscriptions file.
+    fn parse(line: &str) -> Option<(String, Self)> {
+        let mut fields = line.split('\t');
+        let (name, url) = fields.next()?.split_once(',')?;
+
+        let mut webhook = Self::new(url.to_string(), PayloadFormat::Generic);
+        for field in fields {
+            match field.split_once('=') {
+                Some(("format", value)) => {
+                    webhook.format = PayloadFormat::from_str(value, true).ok()?
+                }
+                Some(("feeds", value)) => webhook.feeds = parse_names(value),
+                Some(("tags", value)) => webhook.tags = parse_tags(value),
+                _ => {}
+            }
+        }
+
+        Some((name.to_string(), webhook))
+    }
+
+    /// Format the webhook as a line in the webhooks file.
+    fn to_line(&self, name: &str) -> String {
+        let mut line = format!("{},{}\tformat={}", name, self.url, self.format);
+        if !self.feeds.is_empty() {
+            line.push_str("\tfeeds=");
+            line.push_str(&self.feeds.iter().cloned().collect::<Vec<_>>().join(","));
+        }
+        if !self.tags.is_empty() {
+            line.push_str("\ttags=");
+            line.push_str(&self.tags.iter().cloned().collect::<Vec<_>>().join(","));
+        }
+
+        line
+    }
+}
+
+/// Parse a comma separated list of feed names.
+pub fn parse_names(names: &str) -> BTreeSet<String> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// A delivery to a webhook which failed, even after retrying.
+pub struct DeliveryFailure {
+    /// The name of the webhook
+    pub webhook: String,
+
+    /// The name of the feed whose items were being delivered
+    pub feed: String,
+
+    /// The reason the delivery failed
+    pub error: Error,
+}
+
+impl fmt::Display for DeliveryFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to deliver new posts of '{}' to webhook '{}': {}",
+            self.feed, self.webhook, self.error
+        )
+    }
+}
+
+/// The configured webhooks.
+pub struct Webhooks {
+    webhooks: BTreeMap<String, Webhook>,
+    path: PathBuf,
+}
+
+impl Webhooks {
+    /// Read the webhooks stored next to the subscriptions file `subscriptions_file`.
+    pub fn next_to(subscriptions_file: &Path) -> Result<Self> {
+        let path = subscriptions_file.with_file_name(WEBHOOKS_FILE);
+
+        let mut webhooks = BTreeMap::new();
+        for line in read_lines(&path)? {
+            if let Some((name, webhook)) = Webhook::parse(&line) {
+                webhooks.insert(name, webhook);
+            }
+        }
+
+        Ok(Self { webhooks, path })
+    }
+
+    /// Add a webhook.
+    pub fn add(&mut self, name: String, mut webhook: Webhook) -> Result<()> {
+        // Lines of the webhooks file have the same form as those of the subscriptions file.
+        if name.trim().is_empty() || name.contains([',', '\t', '\r', '\n']) {
+            return Err(Error::InvalidFeedName(name));
+        }
+        if let Some(feed) = webhook
+            .feeds
+            .iter()
+            .find(|feed| feed.contains(['\t', '\r', '\n']))
+        {
+            return Err(Error::InvalidFeedName(feed.to_string()));
+        }
+        webhook.url = match Url::parse(&webhook.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url.to_string(),
+            _ => return Err(Error::InvalidWebhookUrl(webhook.url)),
+        };
+
+        match self.webhooks.entry(name.clone()) {
+            Entry::Occupied(entry) => Err(Error::DuplicateWebhook {
+                name,
+                new_url: webhook.url,
+                old_url: entry.get().url.to_string(),
+            }),
+            Entry::Vacant(entry) => {
+                entry.insert(webhook);
+                self.save()
+            }
+        }
+    }
+
+    /// Remove a webhook, returning its url if it existed.
+    pub fn remove(&mut self, name: &str) -> Result<Option<String>> {
+        match self.webhooks.remove(name) {
+            Some(webhook) => {
+                self.save()?;
+                Ok(Some(webhook.url))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the webhooks, by name.
+    pub fn webhooks(&self) -> &BTreeMap<String, Webhook> {
+        &self.webhooks
+    }
+
+    /// Write all webhooks to the webhooks file, replacing its current contents.
+    fn save(&self) -> Result<()> {
+        write_lines(
+            &self.path,
+            self.webhooks
+                .iter()
+                .map(|(name, webhook)| webhook.to_line(name)),
+        )
+    }
+
+    /// Deliver the items of `feed`, the feed of `subscription`, to every webhook which accepts the
+    /// feed. Items which `log` shows have already been delivered to a webhook are not delivered to
+    /// it again. Returns the deliveries which failed.
+    pub fn deliver(
+        &self,
+        client: &HttpClient,
+        log: &mut DeliveryLog,
+        feed: &Feed,
+        subscription: &Subscription,
+    ) -> Vec<DeliveryFailure> {
+        let mut failures = vec![];
+        for (name, webhook) in &self.webhooks {
+            if !webhook.accepts(feed.name(), subscription) {
+                continue;
+            }
+
+            let items: Vec<_> = feed
+                .items
+                .iter()
+                .filter(|item| !log.contains(name, feed, item))
+                .collect();
+
+            for (items, payload) in payloads(webhook.format, feed, &items) {
+                let result = post(client, &webhook.url, &payload);
+                // A payload which the server rejects is never going to be accepted, so its items
+                // are logged as if they were delivered, rather than being retried forever.
+                let rejected = matches!(&result, Err(error) if is_rejected(error));
+                let result = if result.is_ok() || rejected {
+                    log.record(name, feed, &items).and(result)
+                } else {
+                    result
+                };
+
+                if let Err(error) = result {
+                    failures.push(DeliveryFailure {
+                        webhook: name.clone(),
+                        feed: feed.name().to_string(),
+                        error,
+                    });
+
+                    // Don't deliver the rest out of order, they are retried with the next items.
+                    if !rejected {
+                        break;
+                    }
+                }
+            }
+        }
+
+        failures
+    }
+
+    /// Post a test item to the webhook with the given name, without logging the delivery.
+    pub fn send_test(&self, client: &HttpClient, name: &str) -> Result<()> {
+        let webhook = self
+            .webhooks
+            .get(name)
+            .ok_or_else(|| Error::UnknownWebhook(name.to_string()))?;
+
+        let item = FeedItem {
+            title: String::from("Test post from gobbler"),
+            id: String::from("https://github.com/KnapSac/gobbler"),
+            timestamp: Local::now().fixed_offset(),
+            published: None,
+            summary: Some(String::from("If you can read this, the webhook works.")),
+            author: None,
+            categories: vec![],
+            content: None,
+            enclosures: vec![],
+        };
+        let feed = Feed::new(
+            String::from("gobbler"),
+            String::from("https://github.com/KnapSac/gobbler"),
+            vec![item],
+        )?;
+
+        for (_, payload) in payloads(
+            webhook.format,
+            &feed,
+            &feed.items.iter().collect::<Vec<_>>(),
+        ) {
+            post(client, &webhook.url, &payload)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The log of which items have been delivered to which webhooks, which is only needed by the
+/// commands which deliver items.
+pub struct DeliveryLog {
+    /// The items which have been delivered, as `webhook\tfeed\tid`
+    delivered: HashSet<String>,
+    path: PathBuf,
+}
+
+impl DeliveryLog {
+    /// Read the delivery log stored next to the subscriptions file `subscriptions_file`. Only the
+    /// last [`DELIVERIES_PER_FEED`] deliveries of every feed to every webhook are kept.
+    pub fn next_to(subscriptions_file: &Path) -> Result<Self> {
+        let path = subscriptions_file.with_file_name(DELIVERY_LOG_FILE);
+        let delivered = read_log(&path, webhook_and_feed_of, DELIVERIES_PER_FEED)?;
+
+        Ok(Self {
+            delivered: delivered.into_iter().collect(),
+            path,
+        })
+    }
+
+    /// Check whether `item` of `feed` has been delivered to the webhook `name`.
+    fn contains(&self, name: &str, feed: &Feed, item: &FeedItem) -> bool {
+        self.delivered.contains(&log_entry(name, feed, item))
+    }
+
+    /// Record that `items` of `feed` have been delivered to the webhook `name`.
+    fn record(&mut self, name: &str, feed: &Feed, items: &[&FeedItem]) -> Result<()> {
+        let entries: Vec<_> = items
+            .iter()
+            .map(|item| log_entry(name, feed, item))
+            .collect();
+        append_lines(&self.path, &entries)?;
+        self.delivered.extend(entries);
+
+        Ok(())
+    }
+}
+
+/// Get the entry in the delivery log for delivering `item` of `feed` to the webhook `name`. Ids can
+/// contain anything, so every part is escaped to keep the entry on a single line.
+fn log_entry(name: &str, feed: &Feed, item: &FeedItem) -> String {
+    format!(
+        "{}\t{}\t{}",
+        escape(name),
+        escape(feed.name()),
+        escape(&item.id)
+    )
+}
+
+/// Get the part of an entry in the delivery log which names the webhook and the feed.
+fn webhook_and_feed_of(entry: &str) -> &str {
+    match entry.match_indices('\t').nth(1) {
+        Some((index, _)) => &entry[..index],
+        None => entry,
+    }
+}
+
+/// Build the payloads which deliver `items` of `feed` in the given format, together with the items
+/// each payload contains. Chat messages have a maximum length, so multiple messages may be needed.
+fn payloads<'a>(
+    format: PayloadFormat,
+    feed: &Feed,
+    items: &[&'a FeedItem],
+) -> Vec<(Vec<&'a FeedItem>, String)> {
+    if items.is_empty() {
+        return vec![];
+    }
+
+    let (heading, max_length) = match format {
+        PayloadFormat::Generic => {
+            let exported: Vec<_> = items
+                .iter()
+                .filter_map(|item| feed.export_item(item).ok())
+                .collect();
+            let payload = json!({ "feed": feed.name(), "items": exported });
+
+            return vec![(items.to_vec(), payload.to_string())];
+        }
+        PayloadFormat::Slack => (
+            format!("*{}*", escape_slack(feed.name())),
+            SLACK_MESSAGE_LENGTH,
+        ),
+        PayloadFormat::Discord => (
+            format!("**{}**", escape_discord(feed.name())),
+            DISCORD_MESSAGE_LENGTH,
+        ),
+    };
+
+    let mut messages: Vec<(Vec<&FeedItem>, String)> = vec![];
+    for item in items {
+        let url = match feed.post_url(item) {
+            Ok(url) => url.to_string(),
+            Err(_) => item.id.clone(),
+        };
+        let line_with = |title: &str| match format {
+            PayloadFormat::Slack => format!("• <{}|{}>", url, title),
+            _ => format!("- [{}](<{}>)", title, url),
+        };
+        // Every line has to fit in a message of its own, below the heading, as longer messages
+        // are rejected, so long titles are cut short.
+        let room =
+            max_length.saturating_sub(heading.chars().count() + 1 + line_with("").chars().count());
+        let title = match format {
+            PayloadFormat::Slack => fit(&item.title, escape_slack, room),
+            _ => fit(&item.title, escape_discord, room),
+        };
+        let line = line_with(&title);
+
+        match messages.last_mut() {
+            Some((items, message))
+                if message.chars().count() + 1 + line.chars().count() <= max_length =>
+            {
+                message.push('\n');
+                message.push_str(&line);
+                items.push(item);
+            }
+            _ => messages.push((vec![item], format!("{}\n{}", heading, line))),
+        }
+    }
+
+    let key = match format {
+        PayloadFormat::Slack => "text",
+        _ => "content",
+    };
+    messages
+        .into_iter()
+        .map(|(items, message)| (items, json!({ key: message }).to_string()))
+        .collect()
+}
+
+/// Escape `title` using `escape`, cutting it short with an ellipsis if the escaped title would be
+/// longer than `room` characters. Escape sequences are never cut in half.
+fn fit(title: &str, escape: fn(&str) -> String, room: usize) -> String {
+    let escaped = escape(title);
+    if escaped.chars().count() <= room {
+        return escaped;
+    }
+
+    // Leave room for the ellipsis.
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in title.chars() {
+        let escaped = escape(c.encode_utf8(&mut [0; 4]));
+        used += escaped.chars().count();
+        if used > room.saturating_sub(1) {
+            break;
+        }
+        fitted.push_str(&escaped);
+    }
+    fitted.push('…');
+
+    fitted
+}
+
+/// Escape the characters which have a special meaning in Slack messages.
+fn escape_slack(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escape the characters which have a special meaning in Discord messages.
+fn escape_discord(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`' | '[' | ']' | '|' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/// Check whether `error`, which was returned by [`post`], means that the server rejected the
+/// payload itself, rather than that the webhook can't be reached or is misconfigured.
+fn is_rejected(error: &Error) -> bool {
+    match error {
+        Error::Http(error) => matches!(**error, ureq::Error::Status(400 | 413 | 422, _)),
+        _ => false,
+    }
+}
+
+/// Post the JSON `payload` to `url`, retrying with an increasing delay when the server is
+/// unavailable, overloaded or can't be reached.
+fn post(client: &HttpClient, url: &str, payload: &str) -> Result<()> {
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        let error = match client
+            .post(url)
+            .set("Content-Type", "application/json")
+            .send_string(payload)
+        {
+            Ok(_) => return Ok(()),
+            Err(error) => error,
+        };
+
+        let retry_after = match &error {
+            ureq::Error::Status(status, response) if *status == 429 || *status >= 500 => response
+                .header("Retry-After")
+                .and_then(|seconds| seconds.trim().parse().ok())
+                .map(|seconds| Duration::from_secs(seconds).min(MAX_RETRY_DELAY)),
+            ureq::Error::Status(_, _) => return Err(client.error(url, error)),
+            ureq::Error::Transport(_) => None,
+        };
+        if attempt == MAX_ATTEMPTS {
+            return Err(client.error(url, error));
+        }
+
+        thread::sleep(retry_after.unwrap_or(delay));
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use std::{
+        env, fs, process,
+        sync::{Arc, Mutex},
+    };
+    use tiny_http::{Response, Server};
+
+    fn item(title: &str) -> FeedItem {
+        FeedItem {
+            title: title.to_string(),
+            id: format!("/posts/{}", title.replace(' ', "-")),
+            timestamp: DateTime::parse_from_rfc3339("2024-05-01T10:00:00+02:00").unwrap(),
+            published: None,
+            summary: None,
+            author: None,
+            categories: vec![],
+            content: None,
+            enclosures: vec![],
+        }
+    }
+
+    fn feed(items: Vec<FeedItem>) -> Feed {
+        Feed::new(
+            String::from("Blog"),
+            String::from("https://example.com/feed.xml"),
+            items,
+        )
+        .unwrap()
+    }
+
+    /// A directory of its own for the state files of a test.
+    fn state_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("gobbler-webhook-{}-{}", test, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Start a server which answers the requests it receives with `statuses`, and with `200 OK`
+    /// once those run out. Returns the url of the server and the bodies of the requests.
+    fn stub_server(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr());
+        let bodies = Arc::new(Mutex::new(vec![]));
+
+        let received = bodies.clone();
+        thread::spawn(move || {
+            let mut statuses = statuses.into_iter();
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                received.lock().unwrap().push(body);
+
+                let status = statuses.next().unwrap_or(200);
+                let retry_after = "Retry-After: 0".parse::<tiny_http::Header>().unwrap();
+                let _ = request.respond(Response::empty(status).with_header(retry_after));
+            }
+        });
+
+        (url, bodies)
+    }
+
+    /// Deliver `feed` to a single webhook posting to `url`, with the state stored in `dir`.
+    fn deliver(dir: &Path, url: &str, format: PayloadFormat, feed: &Feed) -> Vec<DeliveryFailure> {
+        let subscriptions_file = dir.join("subscriptions.db");
+        let mut webhooks = Webhooks::next_to(&subscriptions_file).unwrap();
+        if webhooks.webhooks().is_empty() {
+            webhooks
+                .add(String::from("team"), Webhook::new(url.to_string(), format))
+                .unwrap();
+        }
+        let mut log = DeliveryLog::next_to(&subscriptions_file).unwrap();
+        let subscription = Subscription::new(feed.url().to_string());
+
+        webhooks.deliver(&HttpClient::new(), &mut log, feed, &subscription)
+    }
+
+    #[test]
+    fn splits_long_messages() {
+        let items: Vec<_> = (0..60)
+            .map(|i| item(&format!("A rather long title of post number {} [draft]", i)))
+            .collect();
+        let feed = feed(items);
+        let items: Vec<_> = feed.items.iter().collect();
+
+        for (format, max_length) in [
+            (PayloadFormat::Slack, SLACK_MESSAGE_LENGTH),
+            (PayloadFormat::Discord, DISCORD_MESSAGE_LENGTH),
+        ] {
+            let payloads = payloads(format, &feed, &items);
+            assert!(payloads.len() > 1);
+            assert_eq!(
+                payloads.iter().map(|(items, _)| items.len()).sum::<usize>(),
+                60
+            );
+            for (_, payload) in &payloads {
+                let payload: serde_json::Value = serde_json::from_str(payload).unwrap();
+                let message = payload.as_object().unwrap().values().next().unwrap();
+                let message = message.as_str().unwrap();
+                assert!(message.starts_with("*Blog*\n") || message.starts_with("**Blog**\n"));
+                assert!(message.chars().count() <= max_length);
+            }
+        }
+
+        assert_eq!(payloads(PayloadFormat::Generic, &feed, &items).len(), 1);
+        assert!(payloads(PayloadFormat::Discord, &feed, &[]).is_empty());
+    }
+
+    #[test]
+    fn cuts_long_titles_short() {
+        let mut long = item("long");
+        long.title = "*_".repeat(3000);
+        let feed = feed(vec![long, item("short")]);
+        let items: Vec<_> = feed.items.iter().collect();
+
+        for (format, max_length) in [
+            (PayloadFormat::Slack, SLACK_MESSAGE_LENGTH),
+            (PayloadFormat::Discord, DISCORD_MESSAGE_LENGTH),
+        ] {
+            let payloads = payloads(format, &feed, &items);
+            assert_eq!(payloads.len(), 2);
+            let payload: serde_json::Value = serde_json::from_str(&payloads[0].1).unwrap();
+            let message = payload.as_object().unwrap().values().next().unwrap();
+            let message = message.as_str().unwrap();
+            assert!(message.chars().count() <= max_length);
+            assert!(message.contains('…'));
+        }
+
+        assert_eq!(fit("a*b", escape_discord, 5), "a\\*b");
+        assert_eq!(fit("a*b", escape_discord, 3), "a…");
+        assert_eq!(fit("a*bc", escape_discord, 4), "a\\*…");
+        assert_eq!(fit("a&b", escape_slack, 4), "a…");
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(escape_slack("<a> & <b>"), "&lt;a&gt; &amp; &lt;b&gt;");
+        assert_eq!(escape_discord("*bold* [link]"), "\\*bold\\* \\[link\\]");
+    }
+
+    #[test]
+    fn validates_webhooks() {
+        let dir = state_dir("validate");
+        let mut webhooks = Webhooks::next_to(&dir.join("subscriptions.db")).unwrap();
+        let webhook = |url: &str| Webhook::new(url.to_string(), PayloadFormat::Slack);
+
+        for name in ["", " ", "a,b", "a\tb", "a\nb"] {
+            assert!(matches!(
+                webhooks.add(name.to_string(), webhook("https://example.com/")),
+                Err(Error::InvalidFeedName(_))
+            ));
+        }
+        for url in ["not a url", "file:///etc/passwd", "javascript:alert(1)"] {
+            assert!(matches!(
+                webhooks.add(String::from("team"), webhook(url)),
+                Err(Error::InvalidWebhookUrl(_))
+            ));
+        }
+
+        webhooks
+            .add(String::from("team"), webhook("https://example.com/a\tb"))
+            .unwrap();
+        assert!(matches!(
+            webhooks.add(String::from("team"), webhook("https://example.com/")),
+            Err(Error::DuplicateWebhook { .. })
+        ));
+
+        let webhooks = Webhooks::next_to(&dir.join("subscriptions.db")).unwrap();
+        assert_eq!(webhooks.webhooks()["team"].url, "https://example.com/ab");
+    }
+
+    #[test]
+    fn retries_deliveries() {
+        let dir = state_dir("retry");
+        let (url, bodies) = stub_server(vec![503, 429]);
+        let feed = feed(vec![item("one"), item("two")]);
+
+        let failures = deliver(&dir, &url, PayloadFormat::Slack, &feed);
+        assert!(failures.is_empty());
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies.iter().all(|body| *body == bodies[0]));
+        assert!(bodies[0].contains("two"));
+    }
+
+    #[test]
+    fn delivers_items_once() {
+        let dir = state_dir("once");
+        let (url, bodies) = stub_server(vec![]);
+
+        assert!(deliver(&dir, &url, PayloadFormat::Generic, &feed(vec![item("one")])).is_empty());
+        assert_eq!(bodies.lock().unwrap().len(), 1);
+
+        let feed = feed(vec![item("one"), item("two")]);
+        assert!(deliver(&dir, &url, PayloadFormat::Generic, &feed).is_empty());
+        assert!(deliver(&dir, &url, PayloadFormat::Generic, &feed).is_empty());
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 2);
+        assert!(!bodies[1].contains("/posts/one"));
+        assert!(bodies[1].contains("/posts/two"));
+    }
+
+    #[test]
+    fn redelivers_after_failing() {
+        let dir = state_dir("fail");
+        let (url, bodies) = stub_server(vec![503; MAX_ATTEMPTS as usize]);
+        let feed = feed(vec![item("one")]);
+
+        let failures = deliver(&dir, &url, PayloadFormat::Slack, &feed);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].webhook, "team");
+        assert_eq!(bodies.lock().unwrap().len(), MAX_ATTEMPTS as usize);
+
+        assert!(deliver(&dir, &url, PayloadFormat::Slack, &feed).is_empty());
+        assert_eq!(bodies.lock().unwrap().len(), MAX_ATTEMPTS as usize + 1);
+    }
+
+    #[test]
+    fn never_redelivers_rejected_payloads() {
+        let dir = state_dir("reject");
+        let (url, bodies) = stub_server(vec![400]);
+        let items: Vec<_> = (0..60)
+            .map(|i| item(&format!("A rather long title of post number {}", i)))
+            .collect();
+        let feed = feed(items);
+        let payloads = payloads(
+            PayloadFormat::Discord,
+            &feed,
+            &feed.items.iter().collect::<Vec<_>>(),
+        );
+
+        // The rejected payload doesn't hold up the others, and isn't sent again.
+        let failures = deliver(&dir, &url, PayloadFormat::Discord, &feed);
+        assert_eq!(failures.len(), 1);
+        assert!(is_rejected(&failures[0].error));
+        assert_eq!(bodies.lock().unwrap().len(), payloads.len());
+
+        assert!(deliver(&dir, &url, PayloadFormat::Discord, &feed).is_empty());
+        assert_eq!(bodies.lock().unwrap().len(), payloads.len());
+    }
+
+    #[test]
+    fn escapes_log_entries() {
+        let mut item = item("one");
+        item.id = String::from("a\tb\nc");
+        let feed = feed(vec![item]);
+
+        let entry = log_entry("team", &feed, &feed.items[0]);
+        assert_eq!(entry, "team\tBlog\ta\\tb\\nc");
+        assert_eq!(webhook_and_feed_of(&entry), "team\tBlog");
+    }
+}
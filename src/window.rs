@@ -0,0 +1,292 @@
+//! The time window from which feed items are shown, and parsing of the points in time which can be
+//! used to specify it.
+
+use crate::error::*;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeDelta, TimeZone, Utc, Weekday,
+};
+use std::fmt;
+
+/// The window of time from which feed items are shown.
+pub struct TimeWindow {
+    /// Only items after this point in time are shown
+    pub since: DateTime<Utc>,
+
+    /// Only items before this point in time are shown, if set
+    pub until: Option<DateTime<Utc>>,
+
+    /// Describes the window in messages such as "No new posts {description}"
+    description: String,
+}
+
+impl TimeWindow {
+    /// Create a [`TimeWindow`] containing the last `weeks` weeks.
+    pub fn last_weeks(weeks: i64) -> Self {
+        let description = if weeks == 1 {
+            String::from("in the last week")
+        } else {
+            format!("in the last {} weeks", weeks)
+        };
+
+        Self {
+            since: Utc::now() - Duration::weeks(weeks),
+            until: None,
+            description,
+        }
+    }
+
+    /// Create a [`TimeWindow`] containing everything, regardless of when it was posted.
+    pub fn all_time() -> Self {
+        Self {
+            since: DateTime::<Utc>::MIN_UTC,
+            until: None,
+            description: String::from("ever"),
+        }
+    }
+
+    /// Create a [`TimeWindow`] containing everything since `gobbler` was last ran at
+    /// `last_ran_at`.
+    pub fn since_last_run(last_ran_at: DateTime<Utc>) -> Self {
+        Self {
+            since: last_ran_at,
+            until: None,
+            description: String::from("since gobbler last ran"),
+        }
+    }
+
+    /// Create a [`TimeWindow`] containing everything between `since` and `until`.
+    pub fn between(since: DateTime<Utc>, until: Option<DateTime<Utc>>) -> Self {
+        let description = match until {
+            Some(until) => format!("between {} and {}", format_time(since), format_time(until)),
+            None => format!("since {}", format_time(since)),
+        };
+
+        Self {
+            since,
+            until,
+            description,
+        }
+    }
+
+    /// Limit the window to everything before `until`.
+    pub fn with_until(self, until: DateTime<Utc>) -> Self {
+        let description = format!("{} (until {})", self.description, format_time(until));
+
+        Self {
+            until: Some(until),
+            description,
+            ..self
+        }
+    }
+
+    /// Check whether `timestamp` lies after the end of the window.
+    pub fn is_after(&self, timestamp: DateTime<FixedOffset>) -> bool {
+        self.until.map(|until| timestamp > until).unwrap_or(false)
+    }
+
+    /// Check whether `timestamp` lies before the start of the window.
+    pub fn is_before(&self, timestamp: DateTime<FixedOffset>) -> bool {
+        timestamp < self.since
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+/// Format `time` in the local time zone, leaving out the time of day if it is midnight.
+fn format_time(time: DateTime<Utc>) -> String {
+    let time = time.with_timezone(&Local);
+    if time.time() == NaiveTime::MIN {
+        time.format("%Y-%m-%d").to_string()
+    } else {
+        time.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+/// A point in time as passed to `--since` or `--until`. Points in time which name a day, such as
+/// `2024-05-01` or `yesterday`, span that entire day.
+pub struct PointInTime {
+    /// The start of the point in time
+    pub start: DateTime<Utc>,
+
+    /// The end of the point in time
+    pub end: DateTime<Utc>,
+}
+
+impl PointInTime {
+    /// Parse a point in time, relative to `now`. Supported are:
+    /// - ISO 8601 dates and times, such as `2024-05-01`, `2024-05-01T09:00` or
+    ///   `2024-05-01T09:00:00+02:00`,
+    /// - durations relative to now, in hours, days or weeks, such as `36h`, `3d` or `2w`,
+    /// - the keywords `now`, `today`, `yesterday` and `last-monday` through `last-sunday`.
+    pub fn parse(input: &str, now: DateTime<Local>) -> Result<Self> {
+        let input = input.trim();
+        let invalid = || Error::InvalidPointInTime(input.to_string());
+
+        if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+            return Ok(Self::instant(time.with_timezone(&Utc)));
+        }
+
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(input, format) {
+                return Ok(Self::instant(to_utc(time).ok_or_else(invalid)?));
+            }
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+            return Self::day(date).ok_or_else(invalid);
+        }
+
+        let input = input.to_lowercase();
+        let today = now.date_naive();
+        match input.as_str() {
+            "now" => return Ok(Self::instant(now.with_timezone(&Utc))),
+            "today" => return Self::day(today).ok_or_else(invalid),
+            "yesterday" => return Self::day(today - Duration::days(1)).ok_or_else(invalid),
+            _ => {}
+        }
+
+        if let Some(weekday) = input.strip_prefix("last-") {
+            let weekday = weekday.parse::<Weekday>().map_err(|_| invalid())?;
+
+            // The most recent day before today which falls on `weekday`.
+            let mut days_ago =
+                (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+            if days_ago == 0 {
+                days_ago = 7;
+            }
+
+            return Self::day(today - Duration::days(days_ago as i64)).ok_or_else(invalid);
+        }
+
+        if let Some((index, _)) = input.char_indices().last().filter(|(index, _)| *index > 0) {
+            let (amount, unit) = input.split_at(index);
+            if let Ok(amount) = amount.parse::<i64>() {
+                let duration = match unit {
+                    "h" => TimeDelta::try_hours(amount),
+                    "d" => TimeDelta::try_days(amount),
+                    "w" => TimeDelta::try_weeks(amount),
+                    _ => return Err(invalid()),
+                };
+
+                // Durations reaching beyond the range of dates which can be represented are
+                // rejected, rather than overflowing.
+                let time = duration
+                    .and_then(|duration| now.with_timezone(&Utc).checked_sub_signed(duration))
+                    .ok_or_else(invalid)?;
+                return Ok(Self::instant(time));
+            }
+        }
+
+        Err(invalid())
+    }
+
+    /// A point in time which does not span any time.
+    fn instant(time: DateTime<Utc>) -> Self {
+        Self {
+            start: time,
+            end: time,
+        }
+    }
+
+    /// A point in time which spans the entire (local) day `date`.
+    fn day(date: NaiveDate) -> Option<Self> {
+        Some(Self {
+            start: to_utc(date.and_hms_opt(0, 0, 0)?)?,
+            end: to_utc(date.succ_opt()?.and_hms_opt(0, 0, 0)?)?,
+        })
+    }
+}
+
+/// Convert a local date and time to UTC.
+fn to_utc(time: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        // A Wednesday
+        Local.with_ymd_and_hms(2024, 5, 8, 12, 0, 0).unwrap()
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> (DateTime<Utc>, DateTime<Utc>) {
+        let start = Local.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap();
+        (
+            start.with_timezone(&Utc),
+            (start + Duration::days(1)).with_timezone(&Utc),
+        )
+    }
+
+    fn parse(input: &str) -> (DateTime<Utc>, DateTime<Utc>) {
+        let point = PointInTime::parse(input, now()).unwrap();
+        (point.start, point.end)
+    }
+
+    #[test]
+    fn parses_dates_and_times() {
+        let time = DateTime::parse_from_rfc3339("2024-05-01T09:00:00+02:00").unwrap();
+        assert_eq!(
+            parse("2024-05-01T09:00:00+02:00"),
+            (time.into(), time.into())
+        );
+
+        let time = Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap();
+        assert_eq!(parse("2024-05-01T09:00"), (time.into(), time.into()));
+        assert_eq!(parse("2024-05-01 09:00"), (time.into(), time.into()));
+
+        assert_eq!(parse("2024-05-01"), day(2024, 5, 1));
+    }
+
+    #[test]
+    fn parses_keywords() {
+        assert_eq!(parse("now"), (now().into(), now().into()));
+        assert_eq!(parse("today"), day(2024, 5, 8));
+        assert_eq!(parse(" Yesterday "), day(2024, 5, 7));
+        assert_eq!(parse("last-monday"), day(2024, 5, 6));
+        assert_eq!(parse("last-wednesday"), day(2024, 5, 1));
+    }
+
+    #[test]
+    fn parses_durations() {
+        let now = now().with_timezone(&Utc);
+        assert_eq!(parse("36h").0, now - Duration::hours(36));
+        assert_eq!(parse("3d").0, now - Duration::days(3));
+        assert_eq!(parse("2W").0, now - Duration::weeks(2));
+    }
+
+    #[test]
+    fn rejects_invalid_points_in_time() {
+        for input in [
+            "",
+            "h",
+            "3",
+            "3y",
+            "3ü",
+            "ü",
+            "3日",
+            "last-someday",
+            "2024-13-01",
+            "99999999999999w",
+            "9223372036854775807h",
+            "-99999999999999d",
+        ] {
+            assert!(
+                matches!(
+                    PointInTime::parse(input, now()),
+                    Err(Error::InvalidPointInTime(_))
+                ),
+                "{input}"
+            );
+        }
+    }
+}
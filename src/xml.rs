@@ -0,0 +1,34 @@
+//! Helper functions for reading and writing XML.
+
+use windows::{core::HSTRING, Data::Xml::Dom::IXmlNode};
+
+/// Get the value of the attribute `name` of `node`, if it is present and not empty.
+pub(crate) fn get_attribute(node: &IXmlNode, name: &str) -> Option<String> {
+    let value = node
+        .Attributes()
+        .ok()?
+        .GetNamedItem(&HSTRING::from(name))
+        .ok()?
+        .InnerText()
+        .ok()?
+        .to_string_lossy();
+
+    (!value.is_empty()).then_some(value)
+}
+
+/// Escape the characters in `text` which have a special meaning in XML.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}